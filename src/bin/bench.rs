@@ -0,0 +1,85 @@
+use std::time::{Duration, Instant};
+
+use aperiodic_chess::{graph_boards::board_kind::BoardKind, move_generator::MoveTables, notation::coordinate_to_move, position::Position, searcher::Searcher};
+
+// Each benchmark is timed this many times and the fastest run is reported, which filters out most noise
+const SAMPLES: usize = 5;
+
+// Baselines on the traditional board, from a release build (cargo run --release --bin bench).
+// A result more than about twice these is a regression worth looking into
+//   perft depth 5:                     ~0.2 s, 4865609 nodes
+//   legal moves on a midgame, x100000: ~0.1 s, 39 moves each
+//   best move at depth 6:              ~0.35 s, 262230 nodes
+const PERFT_DEPTH: u8 = 5;
+const LEGAL_MOVE_ITERATIONS: usize = 100_000;
+const SEARCH_DEPTH: u8 = 6;
+
+// An Italian game a few moves in, with every piece type still able to move
+const MIDGAME_MOVES: [&str; 10] = ["e2e4", "e7e5", "g1f3", "b8c6", "f1c4", "g8f6", "d2d3", "f8c5", "b1c3", "d7d6"];
+
+
+fn fastest<F: FnMut() -> u64>(mut bench: F) -> (Duration, u64) {
+    let mut best = Duration::MAX;
+    let mut output = 0;
+    for _ in 0..SAMPLES {
+        let start = Instant::now();
+        output = bench();
+        best = best.min(start.elapsed());
+    }
+    return (best, output)
+}
+
+fn report(name: &str, (elapsed, output): (Duration, u64)) {
+    println!("{:<32} {:>10.3} ms   ({})", name, elapsed.as_secs_f64() * 1000.0, output);
+}
+
+fn midgame_position(move_tables: &MoveTables) -> Position {
+    let mut position = Position::new_traditional();
+    for coordinate in MIDGAME_MOVES {
+        let next_move = coordinate_to_move(&mut position, coordinate, move_tables).unwrap();
+        position.make_legal_move(&next_move);
+    }
+    return position
+}
+
+fn bench_perft(move_tables: &MoveTables) {
+    let mut position = Position::new_traditional();
+    report(
+        &format!("perft depth {}", PERFT_DEPTH),
+        fastest(|| move_tables.perft(&mut position, PERFT_DEPTH))
+    );
+}
+
+fn bench_legal_moves(move_tables: &MoveTables) {
+    let mut position = midgame_position(move_tables);
+    report(
+        &format!("legal moves x{}", LEGAL_MOVE_ITERATIONS),
+        fastest(|| {
+            let mut total = 0;
+            for _ in 0..LEGAL_MOVE_ITERATIONS {
+                total += move_tables.get_legal_moves(&mut position).len() as u64;
+            }
+            return total
+        })
+    );
+}
+
+fn bench_search() {
+    let mut position = midgame_position(&BoardKind::Traditional.shared_move_tables());
+    report(
+        &format!("best move depth {}", SEARCH_DEPTH),
+        fastest(|| {
+            // A fresh searcher each run, so the transposition table doesn't carry over between samples
+            let mut searcher = Searcher::new(BoardKind::Traditional.shared_move_tables());
+            return searcher.get_best_move(&mut position, SEARCH_DEPTH).stats.nodes as u64
+        })
+    );
+}
+
+fn main() {
+    // Timings for catching large regressions in move generation and search, not precise measurements
+    let move_tables = BoardKind::Traditional.shared_move_tables();
+    bench_perft(&move_tables);
+    bench_legal_moves(&move_tables);
+    bench_search();
+}
@@ -0,0 +1,14 @@
+use std::io::{self, BufRead};
+
+use aperiodic_chess::uci::UciEngine;
+
+
+fn main() -> io::Result<()> {
+    let mut engine = UciEngine::new(io::stdout());
+    for line in io::stdin().lock().lines() {
+        if !engine.handle_command(&line?)? {
+            break
+        }
+    }
+    Ok(())
+}
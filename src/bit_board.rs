@@ -1,23 +1,44 @@
 use std::collections::HashSet;
 use std::ops::{Sub, BitAnd, BitOr, Not, BitAndAssign, BitOrAssign};
 
+use serde::{Deserialize, Serialize};
+
 use crate::piece_set::PieceType;
 use crate::chess_move::{EnPassantData, Move};
+use crate::constants::MAX_NUM_TILES;
 use crate::graph_boards::graph_board::TileIndex;
 
 
-#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
+#[derive(Debug, PartialEq)]
+pub enum BitBoardError {
+    TileOutOfRange(TileIndex)
+}
+
+// Shifting past the u128 would silently wrap in release builds, so board construction bugs fail here instead
+fn tile_mask(tile: &TileIndex) -> u128 {
+    debug_assert!(tile.index() < MAX_NUM_TILES, "tile index {} does not fit in a BitBoard", tile.index());
+    return 1 << tile.index()
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash, Serialize, Deserialize)]
 pub struct BitBoard(pub u128);
 
 impl BitBoard {
     pub fn from_tile_indices(tile_indices: HashSet<TileIndex>) -> BitBoard {
         let mut result: u128 = 0;
         for tile in tile_indices {
-            result += 1 << tile.index();
+            result += tile_mask(&tile);
         }
         return BitBoard(result)
     }
 
+    pub fn checked_from_tile_indices(tile_indices: HashSet<TileIndex>) -> Result<BitBoard, BitBoardError> {
+        if let Some(tile) = tile_indices.iter().find(|tile| tile.index() >= MAX_NUM_TILES) {
+            return Err(BitBoardError::TileOutOfRange(*tile))
+        }
+        return Ok(BitBoard::from_tile_indices(tile_indices))
+    }
+
     pub fn from_ints(ints: Vec<u128>) -> BitBoard {
         let mut result: u128 = 0;
         for tile in ints {
@@ -35,15 +56,32 @@ impl BitBoard {
     }
 
     pub fn get_bit_at_tile(self, tile: &TileIndex) -> bool {
-        let mask: u128 = 1 << tile.index();
+        let mask: u128 = tile_mask(tile);
         return (self.0 & mask) != 0
     }
 
     pub fn flip_bit_at_tile_index(&mut self, tile: TileIndex){
-        let mask: u128 = 1 << tile.index();
+        let mask: u128 = tile_mask(&tile);
         self.0 = self.0 ^ mask
     }
 
+    pub fn checked_flip_bit_at_tile_index(&mut self, tile: TileIndex) -> Result<(), BitBoardError> {
+        if tile.index() >= MAX_NUM_TILES {
+            return Err(BitBoardError::TileOutOfRange(tile))
+        }
+        self.flip_bit_at_tile_index(tile);
+        return Ok(())
+    }
+
+    pub fn shift_direction(&self, offset: i32, mask: BitBoard) -> BitBoard {
+        // Moves every tile in self & mask by offset indices at once, mask drops tiles that would leave the board
+        let masked = self.0 & mask.0;
+        return match offset >= 0 {
+            true => BitBoard(masked << offset),
+            false => BitBoard(masked >> -offset)
+        }
+    }
+
     pub fn is_zero(&self) -> bool {
         if self.0 == 0 {
             return true
@@ -113,13 +151,24 @@ impl Not for BitBoard {
 pub struct CarryRippler {
     mask: BitBoard,
     current_subset: BitBoard,
+    yield_empty: bool
 }
 
 impl CarryRippler {
     pub fn new(mask: BitBoard) -> CarryRippler {
         return CarryRippler {
             mask,
-            current_subset: BitBoard(0)
+            current_subset: BitBoard(0),
+            yield_empty: false
+        }
+    }
+
+    pub fn with_empty(mask: BitBoard) -> CarryRippler {
+        // Yields the empty subset first, so table builders don't have to add it themselves
+        return CarryRippler {
+            mask,
+            current_subset: BitBoard(0),
+            yield_empty: true
         }
     }
 }
@@ -128,6 +177,10 @@ impl Iterator for CarryRippler {
     type Item = BitBoard;
 
     fn next(&mut self) -> Option<Self::Item> {
+        if self.yield_empty {
+            self.yield_empty = false;
+            return Some(self.current_subset)
+        }
         if self.current_subset == self.mask {
             return None
         }
@@ -188,38 +241,37 @@ impl Iterator for BitBoardMoves {
     type Item = Move;
 
     fn next(&mut self) -> Option<Self::Item> {
-        let mut promotion = None;
-        let mut en_passant_tile = None;
-       
         // Need to iterate through the possible promotions if possible
         if let Some(destination_tile) = self.current_promotion_tile {
             self.current_promotion_counter += 1;
             let promotion = match self.current_promotion_counter {
-                1 => Some(PieceType::Bishop), // 0 will already be handled for the Knight
-                2 => Some(PieceType::Rook),
+                1 => PieceType::Bishop, // 0 will already be handled for the Knight
+                2 => PieceType::Rook,
                 _ => { // Reset after Queen
                     self.current_promotion_tile.take();
                     self.current_promotion_counter = 0;
-                    Some(PieceType::Queen)
+                    PieceType::Queen
                 }
             };
-            Some(Move::new(self.source_tile, destination_tile, promotion, en_passant_tile))
-        } else if let Some(destination_tile) = self.remaining_moves.next() {
-            if self.is_pawn {
-                if let Some(data) = &self.next_ep_data {
-                    if data.occupied_tile == destination_tile {
-                        en_passant_tile = Some(data.passed_tile)
-                    }
-                }
-                if self.promotable_tiles.get_bit_at_tile(&destination_tile) { // Handles promotion to Knight
-                    self.current_promotion_tile = Some(destination_tile);
-                    promotion = Some(PieceType::Knight);
+            // A promotion tile is never the tile a pawn passes over, so these can't be en passant
+            return Some(Move::new(self.source_tile, destination_tile, Some(promotion), None))
+        }
+
+        let destination_tile = self.remaining_moves.next()?;
+        let mut promotion = None;
+        let mut en_passant_tile = None;
+        if self.is_pawn {
+            if let Some(data) = &self.next_ep_data {
+                if data.occupied_tile == destination_tile {
+                    en_passant_tile = Some(data.passed_tile)
                 }
             }
-            Some(Move::new(self.source_tile, destination_tile, promotion, en_passant_tile))
-        } else {
-            None
+            if self.promotable_tiles.get_bit_at_tile(&destination_tile) { // Handles promotion to Knight
+                self.current_promotion_tile = Some(destination_tile);
+                promotion = Some(PieceType::Knight);
+            }
         }
+        Some(Move::new(self.source_tile, destination_tile, promotion, en_passant_tile))
     }
 }
 
@@ -235,6 +287,31 @@ mod tests {
         )
     }
 
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic(expected = "does not fit in a BitBoard")]
+    fn test_out_of_range_tile_panics() {
+        let mut bitboard = BitBoard::empty();
+        bitboard.flip_bit_at_tile_index(TileIndex::new(MAX_NUM_TILES));
+    }
+
+    #[test]
+    fn test_checked_out_of_range_tile() {
+        let mut bitboard = BitBoard::empty();
+        assert_eq!(
+            bitboard.checked_flip_bit_at_tile_index(TileIndex::new(MAX_NUM_TILES)),
+            Err(BitBoardError::TileOutOfRange(TileIndex::new(MAX_NUM_TILES)))
+        );
+        assert_eq!(
+            BitBoard::checked_from_tile_indices(HashSet::from_iter([TileIndex::new(0), TileIndex::new(25)])),
+            Ok(BitBoard(33554433))
+        );
+        assert_eq!(
+            BitBoard::checked_from_tile_indices(HashSet::from_iter([TileIndex::new(130)])),
+            Err(BitBoardError::TileOutOfRange(TileIndex::new(130)))
+        )
+    }
+
     #[test]
     fn test_get_bit_at_tile() {
         assert_eq!(
@@ -307,6 +384,18 @@ mod tests {
         )
     }
 
+    #[test]
+    fn test_carry_ripple_with_empty() {
+        assert_eq!(
+            CarryRippler::with_empty(BitBoard(3)).collect::<Vec<BitBoard>>(),
+            vec![BitBoard(0), BitBoard(1), BitBoard(2), BitBoard(3)]
+        );
+        assert_eq!(
+            CarryRippler::with_empty(BitBoard(0)).collect::<Vec<BitBoard>>(),
+            vec![BitBoard(0)]
+        )
+    }
+
     #[test]
     fn test_bitboard_tiles() {
         let bitboard = BitBoard::from_ints(vec![1, 3, 4]);
@@ -444,4 +533,29 @@ mod tests {
             Move::new(source_tile, TileIndex::new(57), Some(PieceType::Knight), None)
         );
     }
+
+    #[test]
+    fn test_bitboard_moves_capturing_promotion() {
+        // Every promotion comes out for the capture, and the en passant data for another tile stays out of them
+        let source_tile = TileIndex::new(48);
+        let remaining_moves = BitBoard::from_ints(vec![57]);
+        let en_passant_data = Some(EnPassantData {
+            source_tile: TileIndex::new(33),
+            passed_tile: TileIndex::new(41),
+            occupied_tile: TileIndex::new(49)
+        });
+        let mut bitboard_moves = BitBoardMoves::new(
+            source_tile, true, remaining_moves, en_passant_data, BitBoard::from_ints(vec![56, 57, 58])
+        );
+        for promotion in [PieceType::Knight, PieceType::Bishop, PieceType::Rook, PieceType::Queen] {
+            assert_eq!(
+                bitboard_moves.next().unwrap(),
+                Move::new(source_tile, TileIndex::new(57), Some(promotion), None)
+            );
+        }
+        assert_eq!(
+            bitboard_moves.next(),
+            None
+        );
+    }
 }
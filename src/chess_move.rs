@@ -1,8 +1,11 @@
+use serde::{Deserialize, Serialize};
+
 use crate::piece_set::PieceType;
 use crate::graph_boards::graph_board::TileIndex;
+use crate::move_generator::MoveTables;
 
 
-#[derive(Debug, PartialEq, Clone)]
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
 pub struct EnPassantData {
     pub source_tile: TileIndex,
     pub passed_tile: TileIndex,
@@ -37,3 +40,88 @@ impl Move {
         return Self { source_tile, destination_tile, promotion, en_passant_data }
     }
 }
+
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct AnnotatedMove {
+    pub chess_move: Move,
+    pub is_capture: bool,
+    pub gives_check: bool,
+    pub is_promotion: bool
+}
+
+
+// Bit layout: source (7 bits), destination (7 bits), promotion (3 bits, 0 for None), double push flag (1 bit)
+const TILE_BITS: u32 = 7;
+const TILE_MASK: u32 = (1 << TILE_BITS) - 1;
+const PROMOTION_SHIFT: u32 = 2 * TILE_BITS;
+const PROMOTION_MASK: u32 = 0b111;
+const DOUBLE_PUSH_SHIFT: u32 = PROMOTION_SHIFT + 3;
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct PackedMove(pub u32);
+
+impl PackedMove {
+    pub fn pack(chess_move: &Move) -> Self {
+        let mut packed = chess_move.source_tile.index() as u32 & TILE_MASK;
+        packed |= (chess_move.destination_tile.index() as u32 & TILE_MASK) << TILE_BITS;
+        if let Some(promotion) = &chess_move.promotion {
+            packed |= (promotion.as_idx() as u32 + 1) << PROMOTION_SHIFT;
+        }
+        if chess_move.en_passant_data.is_some() {
+            packed |= 1 << DOUBLE_PUSH_SHIFT;
+        }
+        Self(packed)
+    }
+
+    pub fn unpack(&self, move_tables: &MoveTables) -> Move {
+        let source_tile = TileIndex::new((self.0 & TILE_MASK) as usize);
+        let destination_tile = TileIndex::new(((self.0 >> TILE_BITS) & TILE_MASK) as usize);
+        let promotion = match (self.0 >> PROMOTION_SHIFT) & PROMOTION_MASK {
+            0 => None,
+            piece => Some(PieceType::from_idx(piece as usize - 1))
+        };
+        // En passant data only depends on the double push, so it can be recovered from the pawn tables
+        let mut en_passant_data = None;
+        if (self.0 >> DOUBLE_PUSH_SHIFT) & 1 == 1 {
+            en_passant_data = [&move_tables.white_pawn_tables, &move_tables.black_pawn_tables]
+                .iter()
+                .filter_map(|pawn_tables| pawn_tables.en_passant_table[source_tile.index()].clone())
+                .find(|data| data.occupied_tile == destination_tile);
+        }
+        Move::from_input(source_tile, destination_tile, promotion, en_passant_data)
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::graph_boards::traditional_board::TraditionalBoardGraph;
+
+    fn test_move_tables() -> MoveTables {
+        TraditionalBoardGraph::new().0.move_tables()
+    }
+
+    #[test]
+    fn test_packed_move_round_trip() {
+        let move_tables = test_move_tables();
+        let moves = [
+            Move::new(TileIndex::new(1), TileIndex::new(18), None, None), // Quiet
+            Move::new(TileIndex::new(12), TileIndex::new(28), None, None), // Non-pawn along a double push
+            Move::new(TileIndex::new(12), TileIndex::new(28), None, Some(TileIndex::new(20))), // White double push
+            Move::new(TileIndex::new(51), TileIndex::new(35), None, Some(TileIndex::new(43))), // Black double push
+            Move::new(TileIndex::new(36), TileIndex::new(43), None, None), // En passant capture
+            Move::new(TileIndex::new(48), TileIndex::new(56), Some(PieceType::Knight), None),
+            Move::new(TileIndex::new(48), TileIndex::new(57), Some(PieceType::Bishop), None),
+            Move::new(TileIndex::new(15), TileIndex::new(7), Some(PieceType::Rook), None),
+            Move::new(TileIndex::new(127), TileIndex::new(0), Some(PieceType::Queen), None) // Largest tile index
+        ];
+        for chess_move in moves {
+            assert_eq!(
+                PackedMove::pack(&chess_move).unpack(&move_tables),
+                chess_move
+            )
+        }
+    }
+}
@@ -1,5 +1,9 @@
+use std::collections::{HashMap, VecDeque};
+use std::sync::Arc;
+
 use crate::movement_tables::{JumpTable, PawnTables, SlideTables};
 use crate::bit_board::{BitBoard, BitBoardTiles};
+use crate::graph_boards::graph_board::TileIndex;
 use crate::piece_set::{Color, PieceSet, PieceType};
 use crate::move_generator::MoveTables;
 use crate::position::Position;
@@ -7,7 +11,7 @@ use crate::constants::{MAX_NUM_TILES, NUM_PIECE_TYPES};
 
 
 // All measured in centipawns
-const PIECE_SCORES: [isize; NUM_PIECE_TYPES] = [
+pub const PIECE_SCORES: [isize; NUM_PIECE_TYPES] = [
     9999, // King
     900,  // Queen
     500,  // Rook
@@ -16,7 +20,8 @@ const PIECE_SCORES: [isize; NUM_PIECE_TYPES] = [
     100   // Pawn
 ];
 pub const CHECKMATED_SCORE: isize = -30000;
-const POSITIONAL_MULTIPLIER: isize = 5;
+// On the traditional board a knight reaches 3 tiles for every 5 a bishop does (averaged over an empty board)
+const TRADITIONAL_KNIGHT_TO_BISHOP_REACH: (isize, isize) = (3, 5);
 
 // Primitive evaluator will use # of possible moves from each square on an empty board
 pub struct MobilityTable(Vec<u32>);
@@ -43,7 +48,7 @@ impl MobilityTable {
         for direction in (initial_direction..table.0.len()).step_by(direction_step) {
             let mut tile_idx = 0;
             for tile in &table[direction].0 {
-                output[tile_idx] += tile.get(&BitBoard::empty()).unwrap().0.count_ones();
+                output[tile_idx] += tile.get(&BitBoard::empty()).0.count_ones();
                 tile_idx += 1;
             }
         }
@@ -55,38 +60,303 @@ impl MobilityTable {
     }
 }
 
+// Everything needed to decide if a pawn wins its race to promotion against the enemy king
+#[derive(Debug, Clone)]
+struct PawnRun {
+    path: BitBoard, // Tiles still to cross, including the promotion tile
+    stoppers: BitBoard, // Enemy pawns here can block or capture the pawn along its path
+    moves_to_promote: u8,
+    promotion_tile: TileIndex
+}
+
+impl PawnRun {
+    fn all_runs(pawn_tables: &PawnTables, enemy_reverse_attacks: &JumpTable) -> Vec<Option<PawnRun>> {
+        let mut output = vec![];
+        for source_tile in 0..pawn_tables.single_table.num_tiles() {
+            let mut path = BitBoard::empty();
+            let mut stoppers = BitBoard::empty();
+            let mut moves_to_promote = 0;
+            let mut current_tile = TileIndex::new(source_tile);
+            while let Some(next_tile) = pawn_tables.single_table[current_tile].lowest_one() {
+                path.flip_bit_at_tile_index(next_tile);
+                stoppers |= enemy_reverse_attacks[next_tile];
+                moves_to_promote += 1;
+                current_tile = next_tile;
+            }
+            if moves_to_promote == 0 {
+                output.push(None); // Already on a promotion tile
+                continue
+            }
+            if pawn_tables.en_passant_table[source_tile].is_some() && moves_to_promote > 1 {
+                moves_to_promote -= 1; // The double push covers two tiles in one move
+            }
+            output.push(Some(PawnRun { path, stoppers: stoppers | path, moves_to_promote, promotion_tile: current_tile }));
+        }
+        output
+    }
+}
+
+fn file_masks(move_tables: &MoveTables) -> Vec<BitBoard> {
+    // A tile's file is the orthogonal line pawns move along through it, on any board
+    let mut output = vec![];
+    for source_tile in 0..move_tables.king_table.num_tiles() {
+        let tile = TileIndex::new(source_tile);
+        let pawn_steps = move_tables.white_pawn_tables.single_table[tile] | move_tables.black_pawn_tables.single_table[tile];
+        let mut file = BitBoard::from_ints(vec![source_tile as u128]);
+        for direction in (0..move_tables.slide_tables.0.len()).step_by(2) {
+            let ray = move_tables.slide_tables[direction][tile].get(&BitBoard::empty());
+            if !(ray & pawn_steps).is_zero() {
+                file |= ray;
+            }
+        }
+        output.push(file);
+    }
+    output
+}
+
+pub fn derive_piece_values(move_tables: &MoveTables) -> [isize; NUM_PIECE_TYPES] {
+    // The knight is scaled by how its reach compares to a bishop's on this board, relative to the traditional board.
+    // More directions give the knight many more jumps, so on the 12 direction hexagonal board it is
+    // noticeably more valuable than on the traditional board (about 390 against 350)
+    let bishop_reach = MobilityTable::from_slides(&move_tables.slide_tables, PieceType::Bishop);
+    let knight_reach = MobilityTable::from_jumps(&move_tables.knight_table);
+    let num_tiles = move_tables.king_table.num_tiles();
+    let bishop_total: isize = bishop_reach.0[..num_tiles].iter().map(|reach| *reach as isize).sum();
+    let knight_total: isize = knight_reach.0.iter().map(|reach| *reach as isize).sum();
+
+    let mut output = PIECE_SCORES;
+    if bishop_total > 0 {
+        let (knight_ratio, bishop_ratio) = TRADITIONAL_KNIGHT_TO_BISHOP_REACH;
+        output[PieceType::Knight.as_idx()] = PIECE_SCORES[PieceType::Knight.as_idx()] * knight_total * bishop_ratio
+            / (bishop_total * knight_ratio);
+    }
+    output
+}
+
+fn king_distances(king_table: &JumpTable) -> Vec<Vec<u8>> {
+    // Breadth first search from every tile, this is the "square of the pawn" metric on any graph
+    let num_tiles = king_table.num_tiles();
+    let mut output = vec![];
+    for source_tile in 0..num_tiles {
+        let mut distances = vec![u8::MAX; num_tiles];
+        distances[source_tile] = 0;
+        let mut queue = VecDeque::from([TileIndex::new(source_tile)]);
+        while let Some(current_tile) = queue.pop_front() {
+            for next_tile in BitBoardTiles::new(king_table[current_tile]) {
+                if distances[next_tile.index()] == u8::MAX {
+                    distances[next_tile.index()] = distances[current_tile.index()] + 1;
+                    queue.push_back(next_tile);
+                }
+            }
+        }
+        output.push(distances);
+    }
+    output
+}
+
+// Everything that sets the evaluator's playing style, terms can be switched off by setting them to 0
+#[derive(Debug, Clone, PartialEq)]
+pub struct EvalParams {
+    pub piece_values: Option<[isize; NUM_PIECE_TYPES]>, // None derives them from the board, Evaluator::new fills them in
+    pub mobility: isize, // Per tile a non-king piece could reach on an empty board
+    pub dynamic_mobility: bool, // Count the tiles reachable around the actual blockers instead, slower but more accurate, read by Evaluator::new
+    pub king_safety: isize, // Penalty per tile the king could reach, outside the endgame
+    pub king_activity: isize, // Bonus per tile the king could reach, in the endgame
+    pub open_file: isize, // Rook or queen on a file with no pawns
+    pub semi_open_file: isize, // Rook or queen on a file with only enemy pawns
+    pub connected_pawn: isize, // Pawn defended by an allied pawn, per defended pawn
+    pub unstoppable_pawn: isize // Pawn the bare enemy king can't catch, in the endgame
+}
+
+impl Default for EvalParams {
+    fn default() -> Self {
+        Self {
+            piece_values: None,
+            mobility: 5,
+            dynamic_mobility: false,
+            king_safety: 5,
+            king_activity: 5,
+            open_file: 25,
+            semi_open_file: 10,
+            connected_pawn: 10,
+            unstoppable_pawn: 500
+        }
+    }
+}
+
+// Leaf evaluations by zobrist key, evaluation is a pure function of the position so nothing is ever stale
+pub struct EvalCache {
+    entries: HashMap<u64, isize>,
+    capacity: usize,
+    pub hits: usize
+}
+
+impl EvalCache {
+    pub fn new(capacity: usize) -> Self {
+        EvalCache { entries: HashMap::with_capacity(capacity), capacity, hits: 0 }
+    }
+
+    fn get(&mut self, zobrist: u64) -> Option<isize> {
+        let score = self.entries.get(&zobrist).copied();
+        if score.is_some() {
+            self.hits += 1;
+        }
+        score
+    }
+
+    fn insert(&mut self, zobrist: u64, score: isize) {
+        // Starts over when full, recent leaves are the most likely to come up again anyway
+        if self.entries.len() >= self.capacity {
+            self.entries.clear();
+        }
+        self.entries.insert(zobrist, score);
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+}
+
 pub struct Evaluator {
+    pub params: EvalParams,
+    pub cache: Option<EvalCache>,
     king: MobilityTable,
     queen: MobilityTable,
     rook: MobilityTable,
     bishop: MobilityTable,
     knight: MobilityTable,
     white_pawn: MobilityTable,
-    black_pawn: MobilityTable
+    black_pawn: MobilityTable,
+    pawn_runs: [Vec<Option<PawnRun>>; 2],
+    pawn_attacks: [JumpTable; 2],
+    king_distances: Vec<Vec<u8>>,
+    files: Vec<BitBoard>,
+    dynamic_tables: Option<Arc<MoveTables>> // Only kept when dynamic mobility is on
 }
 
 impl Evaluator {
-    pub fn new(move_tables: &MoveTables) -> Self {
+    pub fn new(move_tables: Arc<MoveTables>, mut params: EvalParams) -> Self {
+        let dynamic_tables = match params.dynamic_mobility {
+            true => Some(move_tables.clone()),
+            false => None
+        };
+        let move_tables = move_tables.as_ref();
+        params.piece_values.get_or_insert_with(|| derive_piece_values(move_tables));
         Self {
+            cache: None,
+            params,
             king: MobilityTable::from_jumps(&move_tables.king_table),
             queen: MobilityTable::from_slides(&move_tables.slide_tables, PieceType::Queen),
             rook: MobilityTable::from_slides(&move_tables.slide_tables, PieceType::Rook),
             bishop: MobilityTable::from_slides(&move_tables.slide_tables, PieceType::Bishop),
             knight: MobilityTable::from_jumps(&move_tables.knight_table),
             white_pawn: MobilityTable::from_pawn(&move_tables.white_pawn_tables),
-            black_pawn: MobilityTable::from_pawn(&move_tables.black_pawn_tables)
+            black_pawn: MobilityTable::from_pawn(&move_tables.black_pawn_tables),
+            pawn_runs: [
+                PawnRun::all_runs(&move_tables.white_pawn_tables, &move_tables.reverse_black_pawn_table),
+                PawnRun::all_runs(&move_tables.black_pawn_tables, &move_tables.reverse_white_pawn_table)
+            ],
+            pawn_attacks: [
+                move_tables.white_pawn_tables.attack_table.clone(),
+                move_tables.black_pawn_tables.attack_table.clone()
+            ],
+            king_distances: king_distances(&move_tables.king_table),
+            files: file_masks(move_tables),
+            dynamic_tables
         }
     }
+
+    pub fn set_piece_values(&mut self, values: [isize; NUM_PIECE_TYPES]) {
+        // Indexed like PieceType::as_idx, in centipawns
+        self.params.piece_values = Some(values);
+    }
+
+    fn piece_values(&self) -> &[isize; NUM_PIECE_TYPES] {
+        // Always set, Evaluator::new resolves None
+        return self.params.piece_values.as_ref().unwrap()
+    }
+
+    fn open_file_score(&self, position: &Position, color: &Color) -> isize {
+        let pieces = &position.pieces[color.as_idx()];
+        let allied_pawns = pieces.piece_boards[PieceType::Pawn.as_idx()];
+        let enemy_pawns = position.pieces[color.opponent().as_idx()].piece_boards[PieceType::Pawn.as_idx()];
+        let mut score = 0;
+        for tile in BitBoardTiles::new(pieces.orthogonal_sliders()) {
+            let file = self.files[tile.index()];
+            if !(file & allied_pawns).is_zero() {
+                continue
+            }
+            score += match (file & enemy_pawns).is_zero() {
+                true => self.params.open_file,
+                false => self.params.semi_open_file
+            };
+        }
+        score
+    }
+
+    fn connected_pawn_score(&self, position: &Position, color: &Color) -> isize {
+        // Uses the attack tables rather than adjacent files, so it works on any board
+        let pawns = position.pieces[color.as_idx()].piece_boards[PieceType::Pawn.as_idx()];
+        let mut defended = BitBoard::empty();
+        for pawn_tile in BitBoardTiles::new(pawns) {
+            defended |= self.pawn_attacks[color.as_idx()][pawn_tile];
+        }
+        return (defended & pawns).0.count_ones() as isize * self.params.connected_pawn
+    }
+
+    fn unstoppable_pawn_score(&self, position: &Position, color: &Color) -> isize {
+        // Only judged against a bare king (pawns aside), since any other piece could stop the pawn
+        let enemy_pieces = &position.pieces[color.opponent().as_idx()];
+        let enemy_pawns = enemy_pieces.piece_boards[PieceType::Pawn.as_idx()];
+        let enemy_king = enemy_pieces.piece_boards[PieceType::King.as_idx()];
+        if enemy_pieces.occupied != enemy_pawns | enemy_king {
+            return 0
+        }
+        let enemy_king_tile = match enemy_king.lowest_one() {
+            Some(tile) => tile,
+            None => return 0
+        };
+        let occupied = position.pieces[0].occupied | position.pieces[1].occupied;
+        // The defending king gets an extra move if it is their turn
+        let tempo = match position.active_player == *color {
+            true => 0,
+            false => 1
+        };
+
+        let mut score = 0;
+        for pawn_tile in BitBoardTiles::new(position.pieces[color.as_idx()].piece_boards[PieceType::Pawn.as_idx()]) {
+            if let Some(run) = &self.pawn_runs[color.as_idx()][pawn_tile.index()] {
+                if !(run.path & occupied).is_zero() || !(run.stoppers & enemy_pawns).is_zero() {
+                    continue
+                }
+                let king_distance = self.king_distances[enemy_king_tile.index()][run.promotion_tile.index()];
+                if king_distance as isize > run.moves_to_promote as isize + tempo {
+                    score += self.params.unstoppable_pawn;
+                }
+            }
+        }
+        score
+    }
    
     fn pieceset_material_score(&self, piece_set: &PieceSet) -> isize {
         let mut material_score = 0;
         for piece_idx in 0..NUM_PIECE_TYPES {
-            material_score += piece_set.piece_boards[piece_idx].0.count_ones() as isize * PIECE_SCORES[piece_idx]
+            material_score += piece_set.piece_boards[piece_idx].0.count_ones() as isize * self.piece_values()[piece_idx]
         }
         material_score
     }
    
-    fn piece_positional_score(&self, piece_board: BitBoard, piece_type: PieceType, color: &Color) -> isize {
+    fn piece_mobility(&self, piece_board: BitBoard, piece_type: PieceType, color: &Color, occupied: BitBoard, allied: BitBoard) -> isize {
+        // Pawns always use their table, query_piece leaves them to query_pawn
+        if let Some(move_tables) = &self.dynamic_tables {
+            if piece_type != PieceType::Pawn {
+                let mut score = 0;
+                for tile_idx in BitBoardTiles::new(piece_board) {
+                    score += move_tables.legal_targets(&piece_type, tile_idx, occupied, allied).0.count_ones();
+                }
+                return score as isize
+            }
+        }
         let mobility_table = match piece_type {
             PieceType::King => &self.king,
             PieceType::Queen => &self.queen,
@@ -102,55 +372,53 @@ impl Evaluator {
         for tile_idx in BitBoardTiles::new(piece_board) {
             score += mobility_table.0[tile_idx.index()]
         }
-        score as isize * POSITIONAL_MULTIPLIER
+        score as isize
     }
    
-    fn pieceset_positional_score(&self, piece_set: &PieceSet, is_endgame: bool, color: &Color) -> isize {
+    fn pieceset_positional_score(&self, piece_set: &PieceSet, is_endgame: bool, color: &Color, occupied: BitBoard) -> isize {
         let mut score = 0;
-        let king_multi = match is_endgame {
-            true => 1,
-            false => -1
+        // The king hides until the endgame, then it joins in like any other piece
+        let king_weight = match is_endgame {
+            true => self.params.king_activity,
+            false => -self.params.king_safety
         };
         for piece_idx in 0..NUM_PIECE_TYPES {
-            let mut piece_positional_score = self.piece_positional_score(
-                piece_set.piece_boards[piece_idx],
-                PieceType::from_idx(piece_idx),
-                color
-            );
-            if PieceType::from_idx(piece_idx) == PieceType::King {
-                piece_positional_score *= king_multi
-            }
-            score += piece_positional_score
+            let piece_type = PieceType::from_idx(piece_idx);
+            let weight = match piece_type {
+                PieceType::King => king_weight,
+                _ => self.params.mobility
+            };
+            score += self.piece_mobility(piece_set.piece_boards[piece_idx], piece_type, color, occupied, piece_set.occupied) * weight
         }
         score
     }
    
     pub fn evaluate(&self, position: Position) -> isize {
-        let mut score = 0;
-        let player_idx = position.active_player.as_idx();
-        let player_pieceset = &position.pieces[player_idx];
-        let opponent_idx = position.active_player.opponent().as_idx();
-        let opponent_pieceset = &position.pieces[opponent_idx];
-        let mut total_material_score = 0;
-       
-        let player_material = self.pieceset_material_score(player_pieceset);
-        score += player_material;
-        total_material_score += player_material;
-       
-        let opponent_material = self.pieceset_material_score(opponent_pieceset);
-        score -= opponent_material;
-        total_material_score += opponent_material;
-       
-        let is_endgame = total_material_score < 2 * PIECE_SCORES[PieceType::King.as_idx()]
-                                                    + 2 * PIECE_SCORES[PieceType::Queen.as_idx()]
-                                                    + 2 * PIECE_SCORES[PieceType::Rook.as_idx()];
-       
-        score += self.pieceset_positional_score(player_pieceset, is_endgame, &position.active_player);
-        score -= self.pieceset_positional_score(opponent_pieceset, is_endgame, &position.active_player.opponent());
+        return self.static_evaluate(&position)
+    }
+
+    pub fn material_only(&self, position: &Position) -> isize {
+        // Skips every positional term, for the fastest searches and as a self-play baseline
+        let player_material = self.pieceset_material_score(position.active_pieces());
+        let opponent_material = self.pieceset_material_score(position.enemy_pieces());
+        return player_material - opponent_material
+    }
+
+    pub fn cached_evaluate(&mut self, position: &mut Position) -> isize {
+        // static_evaluate, looked up in the cache first when there is one
+        let zobrist = position.record.zobrist;
+        if let Some(score) = self.cache.as_mut().and_then(|cache| cache.get(zobrist)) {
+            return score
+        }
+        let score = self.static_evaluate(position);
+        if let Some(cache) = self.cache.as_mut() {
+            cache.insert(zobrist, score);
+        }
         score
     }
 
-    pub fn static_evaluate(&self, position: &mut Position) -> isize {
+    pub fn static_evaluate(&self, position: &Position) -> isize {
+        // Scored for the side to move, by reference so the search never clones a position at a leaf
         let mut score = 0;
         let player_idx = position.active_player.as_idx();
         let player_pieceset = &position.pieces[player_idx];
@@ -166,12 +434,232 @@ impl Evaluator {
         score -= opponent_material;
         total_material_score += opponent_material;
        
-        let is_endgame = total_material_score < 2 * PIECE_SCORES[PieceType::King.as_idx()]
-                                                    + 2 * PIECE_SCORES[PieceType::Queen.as_idx()]
-                                                    + 2 * PIECE_SCORES[PieceType::Rook.as_idx()];
+        let piece_values = self.piece_values();
+        let is_endgame = total_material_score < 2 * piece_values[PieceType::King.as_idx()]
+                                                    + 2 * piece_values[PieceType::Queen.as_idx()]
+                                                    + 2 * piece_values[PieceType::Rook.as_idx()];
        
-        score += self.pieceset_positional_score(player_pieceset, is_endgame, &position.active_player);
-        score -= self.pieceset_positional_score(opponent_pieceset, is_endgame, &position.active_player.opponent());
+        let occupied = player_pieceset.occupied | opponent_pieceset.occupied;
+        score += self.pieceset_positional_score(player_pieceset, is_endgame, &position.active_player, occupied);
+        score -= self.pieceset_positional_score(opponent_pieceset, is_endgame, &position.active_player.opponent(), occupied);
+        score += self.open_file_score(&position, &position.active_player);
+        score -= self.open_file_score(&position, &position.active_player.opponent());
+        score += self.connected_pawn_score(&position, &position.active_player);
+        score -= self.connected_pawn_score(&position, &position.active_player.opponent());
+        if is_endgame {
+            score += self.unstoppable_pawn_score(&position, &position.active_player);
+            score -= self.unstoppable_pawn_score(&position, &position.active_player.opponent());
+        }
         score
     }
 }
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::graph_boards::traditional_board::TraditionalBoardGraph;
+    use crate::graph_boards::hexagonal_board::HexagonalBoardGraph;
+
+    fn test_evaluator() -> Evaluator {
+        Evaluator::new(Arc::new(TraditionalBoardGraph::new().0.move_tables()), EvalParams::default())
+    }
+
+    #[test]
+    fn test_material_only_ignores_placement() {
+        let evaluator = test_evaluator();
+        let centralized = Position::from_string("4K22N3P29k w -".to_string());
+        let cornered = Position::from_string("N3K11P46k w -".to_string());
+        assert_eq!(
+            evaluator.material_only(&centralized),
+            PIECE_SCORES[PieceType::Knight.as_idx()] + PIECE_SCORES[PieceType::Pawn.as_idx()]
+        );
+        assert_eq!(
+            evaluator.material_only(&centralized),
+            evaluator.material_only(&cornered)
+        );
+        assert_ne!( // The full evaluation does see the difference
+            evaluator.evaluate(centralized),
+            evaluator.evaluate(cornered)
+        )
+    }
+
+    #[test]
+    fn test_eval_cache() {
+        let mut evaluator = test_evaluator();
+        evaluator.cache = Some(EvalCache::new(2));
+        let score = evaluator.cached_evaluate(&mut Position::new_traditional());
+        assert_eq!(
+            evaluator.cached_evaluate(&mut Position::new_traditional()),
+            score
+        );
+        assert_eq!(
+            evaluator.cache.as_ref().unwrap().hits,
+            1
+        );
+        let mut other = Position::from_string("4K22N3P29k w -".to_string());
+        assert_eq!(
+            evaluator.cached_evaluate(&mut other),
+            evaluator.static_evaluate(&other)
+        );
+        let cache = evaluator.cache.as_ref().unwrap();
+        assert_eq!(
+            cache.hits,
+            1
+        );
+        assert_eq!( // The second position was computed fresh and stored
+            cache.len(),
+            2
+        )
+    }
+
+    #[test]
+    fn test_derived_knight_values() {
+        let knight_idx = PieceType::Knight.as_idx();
+        let traditional = derive_piece_values(&TraditionalBoardGraph::new().0.move_tables());
+        let hexagonal = derive_piece_values(&HexagonalBoardGraph::new().0.move_tables());
+        assert_eq!(
+            traditional,
+            PIECE_SCORES
+        );
+        assert!(hexagonal[knight_idx] > traditional[knight_idx])
+    }
+
+    #[test]
+    fn test_custom_knight_value() {
+        let mut evaluator = test_evaluator();
+        let position = Position::from_string("4K22N3P29k w -".to_string());
+        let standard = evaluator.material_only(&position);
+        let mut values = PIECE_SCORES;
+        values[PieceType::Knight.as_idx()] = 250;
+        evaluator.set_piece_values(values);
+        assert_eq!(evaluator.params.piece_values, Some(values));
+        assert_eq!(
+            evaluator.material_only(&position),
+            standard - 100
+        );
+        assert_eq!(
+            evaluator.evaluate(Position::from_string("4K22N3P29k w -".to_string())),
+            test_evaluator().evaluate(Position::from_string("4K22N3P29k w -".to_string())) - 100
+        )
+    }
+
+    #[test]
+    fn test_pawn_outside_the_square() {
+        let evaluator = test_evaluator();
+        // The a4 pawn needs 4 moves, the king on h5 needs 7 to reach a8
+        let outside = Position::from_string("7K16P14k w -".to_string());
+        // From c6 the king reaches a8 in 2 moves
+        let inside = Position::from_string("7K16P17k w -".to_string());
+        let outside_score = evaluator.static_evaluate(&outside);
+        let inside_score = evaluator.static_evaluate(&inside);
+        assert!(outside_score > evaluator.params.unstoppable_pawn);
+        assert!(inside_score < evaluator.params.unstoppable_pawn);
+    }
+
+    #[test]
+    fn test_file_masks() {
+        let evaluator = test_evaluator();
+        assert_eq!(
+            evaluator.files[0],
+            BitBoard::from_ints(vec![0, 8, 16, 24, 32, 40, 48, 56])
+        );
+        assert_eq!( // The last rank still belongs to its file
+            evaluator.files[63],
+            BitBoard::from_ints(vec![7, 15, 23, 31, 39, 47, 55, 63])
+        )
+    }
+
+    #[test]
+    fn test_rook_on_open_file() {
+        let evaluator = test_evaluator();
+        // Rooks have the same mobility on every tile, so only the file differs
+        let open_file = Position::from_string("R6K1P53k w -".to_string());
+        let behind_pawn = Position::from_string("1R5K1P53k w -".to_string());
+        assert_eq!(
+            evaluator.static_evaluate(&open_file) - evaluator.static_evaluate(&behind_pawn),
+            evaluator.params.open_file
+        );
+        // A black pawn on a7 makes the a-file semi-open
+        let semi_open_file = Position::from_string("R6K1P38p14k w -".to_string());
+        assert_eq!(
+            evaluator.open_file_score(&semi_open_file, &Color::White),
+            evaluator.params.semi_open_file
+        )
+    }
+
+    #[test]
+    fn test_connected_pawns() {
+        let mut evaluator = test_evaluator();
+        // The pawn on e3 defends d4, while one on f3 defends nothing
+        let chain = Position::from_string("7K12P6P35k w -".to_string());
+        let disconnected = Position::from_string("7K13P5P35k w -".to_string());
+        assert_eq!(
+            evaluator.connected_pawn_score(&chain, &Color::White),
+            evaluator.params.connected_pawn
+        );
+        assert_eq!(
+            evaluator.connected_pawn_score(&disconnected, &Color::White),
+            0
+        );
+        let with_term = evaluator.static_evaluate(&chain);
+        evaluator.params.connected_pawn = 0;
+        assert_eq!(
+            with_term - evaluator.static_evaluate(&chain),
+            10
+        )
+    }
+
+    #[test]
+    fn test_blocked_pawn_is_not_unstoppable() {
+        let evaluator = test_evaluator();
+        // Black pawn on a6 blocks the a-file
+        let position = Position::from_string("7K16P15p6k w -".to_string());
+        assert_eq!(
+            evaluator.unstoppable_pawn_score(&position, &Color::White),
+            0
+        )
+    }
+
+    #[test]
+    fn test_doubled_mobility_weight() {
+        // A centralised queen can reach 27 tiles on an empty board, the kings are weighted separately
+        let position = Position::from_string("4K22Q32k3 w -".to_string());
+        let move_tables = Arc::new(TraditionalBoardGraph::new().0.move_tables());
+        let default_evaluator = Evaluator::new(move_tables.clone(), EvalParams::default());
+        let mobile_evaluator = Evaluator::new(move_tables, EvalParams { mobility: 10, ..EvalParams::default() });
+        assert_eq!(
+            mobile_evaluator.evaluate(position.clone()) - default_evaluator.evaluate(position),
+            27 * 5
+        )
+    }
+
+    #[test]
+    fn test_dynamic_mobility() {
+        // The rook on a1 is walled in by its own king and pawn, so it can't actually move
+        let position = Position::from_string("RK6P47k7 w -".to_string());
+        let move_tables = Arc::new(TraditionalBoardGraph::new().0.move_tables());
+        let static_evaluator = Evaluator::new(move_tables.clone(), EvalParams::default());
+        let dynamic_evaluator = Evaluator::new(move_tables, EvalParams { dynamic_mobility: true, ..EvalParams::default() });
+        let occupied = position.pieces[0].occupied | position.pieces[1].occupied;
+        let rook = position.pieces[0].piece_boards[PieceType::Rook.as_idx()];
+        assert_eq!(
+            static_evaluator.piece_mobility(rook, PieceType::Rook, &Color::White, occupied, position.pieces[0].occupied),
+            14
+        );
+        assert_eq!(
+            dynamic_evaluator.piece_mobility(rook, PieceType::Rook, &Color::White, occupied, position.pieces[0].occupied),
+            0
+        );
+        assert!(dynamic_evaluator.static_evaluate(&position) < static_evaluator.static_evaluate(&position))
+    }
+
+    #[test]
+    fn test_start_position_is_balanced() {
+        let evaluator = test_evaluator();
+        assert_eq!(evaluator.static_evaluate(&Position::new_traditional()), 0);
+        let mut black_to_move = Position::new_traditional();
+        black_to_move.active_player = Color::Black;
+        assert_eq!(evaluator.static_evaluate(&black_to_move), 0)
+    }
+}
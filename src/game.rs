@@ -1,84 +1,196 @@
-use bevy::prelude::Resource;
+use crate::{bit_board::{BitBoard, BitBoardTiles}, chess_move::Move, graph_boards::{board_kind::BoardKind, graph_board::TileIndex}, piece_set::PieceType, position::{DrawClaim, DrawReason, GameOver, Position, PositionError}, search_handle::SearchHandle, searcher::{SearchResult, Searcher}};
 
-use crate::{bit_board::{BitBoard, BitBoardTiles}, chess_move::Move, graph_boards::{graph_board::TileIndex, traditional_board::TraditionalBoardGraph, uniform_triangle_board::UniformTriangleBoardGraph}, piece_set::{Color, PieceType}, position::{GameOver, Position}, searcher::Searcher};
 
+// Backstop so a game can never run forever, even if every draw rule is somehow avoided
+const MAX_GAME_PLIES: u32 = 1000;
 
-#[derive(Resource)]
+#[cfg_attr(feature = "gui", derive(bevy::prelude::Resource))]
 pub struct Game {
     pub engine: Searcher,
     pub are_players_cpu: [bool; 2],
+    pub cpu_search_depth: u8,
+    pub board_kind: BoardKind,
     pub current_position: Position,
-    // pub board: TraditionalBoardGraph,
-    pub board: UniformTriangleBoardGraph, // TODO: Generalize
     pub game_over_state: Option<GameOver>,
+    pub plies_played: u32,
+    // Legal moves of current_position, shared by highlighting and clicks until a move is made
+    pub legal_moves: Option<Vec<Move>>,
+    pub legal_move_computations: u32,
 }
 
 impl Game {
     pub fn check_if_over(&mut self) -> () {
-        if self.current_position.is_checkmate(&self.engine.movegen) {
-            self.game_over_state = Some(GameOver::Checkmate)
-        } else if self.current_position.is_stalemate(&self.engine.movegen) || self.current_position.fifty_move_draw() {
-            self.game_over_state = Some(GameOver::Draw)
-        } else {
-            self.game_over_state = None
+        self.game_over_state = self.is_over();
+    }
+
+    pub fn is_over(&mut self) -> Option<GameOver> {
+        return match self.current_position.game_over(&self.engine.movegen) {
+            None if self.plies_played >= MAX_GAME_PLIES => Some(GameOver::MoveLimit),
+            game_over => game_over
         }
     }
 
-    pub fn make_cpu_move(&mut self) {
-        let cpu_move = self.engine.get_best_move(&mut self.current_position, 4).best_move.unwrap();
-        self.current_position.make_legal_move(&cpu_move);
+    pub fn legal_moves(&mut self) -> &Vec<Move> {
+        // Computed on first use, so positions that are only displayed cost nothing
+        if self.legal_moves.is_none() {
+            self.legal_moves = Some(self.engine.movegen.get_legal_moves(&mut self.current_position));
+            self.legal_move_computations += 1;
+        }
+        return self.legal_moves.as_ref().unwrap()
     }
 
-    pub fn query_tile(&mut self, tile_index: &TileIndex) -> BitBoard {
-        let white_pieces = &self.current_position.pieces[0];
-        let black_pieces = &self.current_position.pieces[1];
-        let occupied = white_pieces.occupied | black_pieces.occupied;
-        
-        let selected_white = white_pieces.get_piece_at(tile_index);
-        let selected_black = black_pieces.get_piece_at(tile_index);
-        let selected_piece = selected_white.or(selected_black);
-        
-        let (selected_color, allied_occupied, enemy_occupied, pawn_tables) = match black_pieces.get_piece_at(tile_index) {
-            Some(_t) => (Color::Black, black_pieces.occupied, white_pieces.occupied, &self.engine.movegen.black_pawn_tables),
-            _ => (Color::White, white_pieces.occupied, black_pieces.occupied, &self.engine.movegen.white_pawn_tables)
+    fn make_move(&mut self, chess_move: &Move) {
+        self.current_position.make_legal_move(chess_move);
+        self.plies_played += 1;
+        self.legal_moves = None;
+    }
+
+    pub fn can_claim_draw(&self) -> Option<DrawClaim> {
+        if self.game_over_state != None {
+            return None
+        }
+        return self.current_position.draw_claim()
+    }
+
+    pub fn claim_draw(&mut self) -> bool {
+        // Ends the game as a draw if the position allows a claim
+        self.game_over_state = match self.can_claim_draw() {
+            Some(DrawClaim::ThreefoldRepetition) => Some(GameOver::Draw(DrawReason::Repetition)),
+            Some(DrawClaim::FiftyMoves) => Some(GameOver::Draw(DrawReason::FiftyMove)),
+            None => return false
         };
+        return true
+    }
 
-        let mut pseudo_moves =  match selected_piece {
-            Some(PieceType::Pawn) => {
-                self.engine.movegen.query_pawn(&selected_color, *tile_index, &enemy_occupied, occupied, &self.current_position.record.en_passant_data)
-            },
-            None => BitBoard::empty(),
-            _ => { // All non-Pawn PieceTypes
-                self.engine.movegen.query_piece(&selected_piece.unwrap(), *tile_index, occupied) & !allied_occupied
+    pub fn make_cpu_move(&mut self) {
+        // No move is made once the game is over, so CPU-vs-CPU games stop cleanly
+        self.check_if_over();
+        if self.game_over_state != None {
+            return
+        }
+        let search_result = self.engine.get_best_move(&mut self.current_position, self.cpu_search_depth);
+        self.apply_cpu_result(&search_result);
+    }
+
+    pub fn start_cpu_search(&self, searcher: Searcher) -> SearchHandle {
+        // For callers that can't wait, like the GUI, the result is applied with apply_cpu_result
+        return SearchHandle::spawn(searcher, self.current_position.clone(), self.cpu_search_depth, None)
+    }
+
+    pub fn apply_cpu_result(&mut self, search_result: &SearchResult) {
+        if let Some(cpu_move) = &search_result.best_move {
+            self.make_move(cpu_move);
+        }
+        self.check_if_over();
+    }
+
+    pub fn query_tile(&mut self, tile_index: &TileIndex) -> BitBoard {
+        let mut destinations = BitBoard::empty();
+        for chess_move in self.legal_moves() {
+            // Promotions repeat a destination, flipping it twice would clear it
+            if chess_move.source_tile == *tile_index && !destinations.get_bit_at_tile(&chess_move.destination_tile) {
+                destinations.flip_bit_at_tile_index(chess_move.destination_tile);
             }
+        }
+        return destinations
+    }
+
+    pub fn query_tile_detailed(&mut self, tile_index: &TileIndex) -> Vec<(TileIndex, MoveKind)> {
+        // Same destinations as query_tile, each tagged with how the GUI should mark it
+        let destinations = self.query_tile(tile_index);
+        let active_idx = self.current_position.active_player.as_idx();
+        let enemy_occupied = self.current_position.pieces[1 - active_idx].occupied;
+        let is_pawn = self.current_position.pieces[active_idx].get_piece_at(tile_index) == Some(PieceType::Pawn);
+        let promotion_board = match active_idx {
+            0 => self.engine.movegen.white_pawn_tables.promotion_board,
+            _ => self.engine.movegen.black_pawn_tables.promotion_board
         };
+        let en_passant_tile = self.current_position.en_passant_capture_tile();
+
+        let mut detailed = vec![];
+        for destination_tile in BitBoardTiles::new(destinations) {
+            let kind = if is_pawn && promotion_board.get_bit_at_tile(&destination_tile) {
+                MoveKind::Promotion
+            } else if is_pawn && en_passant_tile == Some(destination_tile) {
+                MoveKind::EnPassant
+            } else if enemy_occupied.get_bit_at_tile(&destination_tile) {
+                MoveKind::Capture
+            } else {
+                MoveKind::Quiet
+            };
+            detailed.push((destination_tile, kind));
+        }
+        return detailed
+    }
 
-        for destination_tile in BitBoardTiles::new(pseudo_moves) {
-            let mut promotion: Option<PieceType> = None;
-            if pawn_tables.promotion_board.get_bit_at_tile(&destination_tile) && selected_piece == Some(PieceType::Pawn) {
-                promotion = Some(PieceType::Queen);
+    pub fn captured_pieces(&self) -> (Vec<PieceType>, Vec<PieceType>) {
+        // White's lost pieces then Black's, oldest first, read back through the position records
+        // Each record's captured_piece was taken by the player who moved into it, en passant included
+        let mut lost = [vec![], vec![]];
+        let mut mover = self.current_position.active_player.opponent();
+        let mut record = Some(self.current_position.record.clone());
+        while let Some(current_record) = record {
+            if current_record.previous_record.is_none() {
+                break // The starting position, no move led to it
             }
-            let chess_move = Move::new(*tile_index, destination_tile, promotion, None);
-            if !self.current_position.is_playable_move(&chess_move, &self.engine.movegen) {
-                pseudo_moves.flip_bit_at_tile_index(destination_tile);
+            if let Some(captured_piece) = current_record.captured_piece {
+                lost[mover.opponent().as_idx()].push(captured_piece);
             }
+            mover = mover.opponent();
+            record = current_record.get_previous_record();
         }
+        let [mut white_lost, mut black_lost] = lost;
+        white_lost.reverse();
+        black_lost.reverse();
+        return (white_lost, black_lost)
+    }
 
-        return pseudo_moves
+    pub fn has_moves_from(&mut self, tile_index: &TileIndex) -> bool {
+        return self.legal_moves().iter().any(|chess_move| chess_move.source_tile == *tile_index)
     }
 
-    pub fn attempt_move_input(&mut self, source_tile: &TileIndex, destination_tile: &TileIndex) -> Result<(), ChessError> {
-        let chess_move = self.parse_move_input(source_tile, destination_tile)?;
-        match self.current_position.is_playable_move(&chess_move, &self.engine.movegen) {
+    pub fn load_position(&mut self, position: Position) -> Result<(), Vec<PositionError>> {
+        // Loaded or edited positions are validated before play continues from them
+        position.validate(&self.engine.movegen)?;
+        self.current_position = position;
+        self.plies_played = 0;
+        self.legal_moves = None;
+        self.check_if_over();
+        Ok(())
+    }
+
+    pub fn is_promotion_input(&self, source_tile: &TileIndex, destination_tile: &TileIndex) -> bool {
+        // Lets the GUI ask which piece to promote to before attempting the move
+        let active_pieces = &self.current_position.pieces[self.current_position.active_player.as_idx()];
+        let promotion_board = match self.current_position.active_player.as_idx() {
+            0 => self.engine.movegen.white_pawn_tables.promotion_board,
+            _ => self.engine.movegen.black_pawn_tables.promotion_board
+        };
+        return active_pieces.get_piece_at(source_tile) == Some(PieceType::Pawn) && promotion_board.get_bit_at_tile(destination_tile)
+    }
+
+    pub fn attempt_move_input(&mut self, source_tile: &TileIndex, destination_tile: &TileIndex, promotion: Option<PieceType>) -> Result<(), ChessError> {
+        // promotion is only read for moves onto a promotion tile, None promotes to a Queen
+        let chess_move = self.parse_move_input(source_tile, destination_tile, promotion)?;
+        match self.legal_moves().contains(&chess_move) {
             true => {
-                self.current_position.make_legal_move(&chess_move);
+                self.make_move(&chess_move);
                 return Ok(())
             },
             false => return Err(ChessError::InvalidMoveError)
         }
     }
 
-    fn parse_move_input(&self, source_tile: &TileIndex, destination_tile: &TileIndex) -> Result<Move, ChessError> {
+    pub fn attempt_move_input_scored(&mut self, source_tile: &TileIndex, destination_tile: &TileIndex, promotion: Option<PieceType>) -> Result<MoveFeedback, ChessError> {
+        self.attempt_move_input(source_tile, destination_tile, promotion)?;
+        let mover = self.current_position.active_player.opponent();
+        // static_evaluate scores for the side to move, which is now the opponent
+        let evaluation = -self.engine.evaluator.static_evaluate(&self.current_position);
+        let gives_check = self.current_position.is_in_check(&self.engine.movegen, &mover.opponent());
+        return Ok(MoveFeedback { evaluation, gives_check })
+    }
+
+    fn parse_move_input(&self, source_tile: &TileIndex, destination_tile: &TileIndex, promotion: Option<PieceType>) -> Result<Move, ChessError> {
         // Assumes destination is valid due to limiting the selectable tiles
         let active_pieces = &self.current_position.pieces[self.current_position.active_player.as_idx()];
 
@@ -103,16 +215,10 @@ impl Game {
             None => None
         };
 
-        let mut promotion = None;
-        if active_pieces.get_piece_at(source_tile) == Some(PieceType::Pawn) {
-            let promotion_board = match self.current_position.active_player.as_idx() {
-                0 => self.engine.movegen.white_pawn_tables.promotion_board,
-                _ => self.engine.movegen.black_pawn_tables.promotion_board
-            };
-            if promotion_board.get_bit_at_tile(destination_tile) {
-                promotion = Some(PieceType::Queen)
-            }
-        }
+        let promotion = match self.is_promotion_input(source_tile, destination_tile) {
+            true => Some(promotion.unwrap_or(PieceType::Queen)),
+            false => None
+        };
         
         return Ok(Move::from_input(
             *source_tile,
@@ -123,7 +229,236 @@ impl Game {
     }
 }
 
+// How a destination is marked in the GUI, promotions take precedence over captures
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MoveKind {
+    Quiet,
+    Capture,
+    EnPassant,
+    Promotion
+}
+
+// Shown to a human after their move, the evaluation is from the mover's point of view
+#[derive(Debug, Clone, PartialEq)]
+pub struct MoveFeedback {
+    pub evaluation: isize,
+    pub gives_check: bool
+}
+
 #[derive(Debug, Clone, PartialEq)]
 pub enum ChessError {
     InvalidMoveError
 }
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::notation::coordinate_to_move;
+    use crate::piece_set::Color;
+
+    fn test_game(current_position: Position, board_kind: BoardKind) -> Game {
+        Game {
+            engine: Searcher::new(board_kind.shared_move_tables()),
+            are_players_cpu: [true, true],
+            cpu_search_depth: 2,
+            board_kind,
+            current_position,
+            game_over_state: None,
+            plies_played: 0,
+            legal_moves: None,
+            legal_move_computations: 0
+        }
+    }
+
+    fn test_cpu_game(current_position: Position) -> Game {
+        return test_game(current_position, BoardKind::Triangular)
+    }
+
+    fn test_traditional_game(current_position: Position) -> Game {
+        return test_game(current_position, BoardKind::Traditional)
+    }
+
+    #[test]
+    fn test_has_moves_from() {
+        let mut game = test_traditional_game(Position::from_string("1N2K7B47r2k w -".to_string()));
+        // The bishop on e2 is pinned to the e-file, where it cannot move
+        assert!(!game.has_moves_from(&TileIndex::new(12)));
+        assert!(game.has_moves_from(&TileIndex::new(1)));
+        assert!(!game.has_moves_from(&TileIndex::new(2))); // Empty tile
+        assert!(!game.has_moves_from(&TileIndex::new(60))); // Not the active player's piece
+    }
+
+    #[test]
+    fn test_threefold_is_claimable_before_fivefold_is_automatic() {
+        let mut game = test_traditional_game(Position::new_traditional());
+        let knight_moves = [(6, 21), (62, 45), (21, 6), (45, 62)];
+        let shuffle = |game: &mut Game| {
+            for (source, destination) in knight_moves {
+                game.current_position.make_legal_move(&Move::new(TileIndex::new(source), TileIndex::new(destination), None, None));
+            }
+            game.check_if_over();
+        };
+        shuffle(&mut game);
+        assert_eq!(game.can_claim_draw(), None); // Second occurrence
+        shuffle(&mut game);
+        assert_eq!(game.can_claim_draw(), Some(DrawClaim::ThreefoldRepetition));
+        assert_eq!(game.game_over_state, None);
+        shuffle(&mut game);
+        assert_eq!(game.game_over_state, None); // Fourth occurrence
+        shuffle(&mut game);
+        assert_eq!(game.game_over_state, Some(GameOver::Draw(DrawReason::Repetition)));
+        assert_eq!(game.can_claim_draw(), None);
+    }
+
+    #[test]
+    fn test_stalemate_is_reported() {
+        let mut game = test_traditional_game(Position::from_string("K52Q9k b -".to_string()));
+        game.check_if_over();
+        assert_eq!(game.game_over_state, Some(GameOver::Draw(DrawReason::Stalemate)));
+        assert_eq!(game.game_over_state.unwrap().display(), "Stalemate — draw.");
+        game.current_position = Position::from_string("r6K1r53k w -".to_string());
+        assert_eq!(game.is_over(), Some(GameOver::Checkmate(Color::Black)));
+        assert_eq!(GameOver::Checkmate(Color::Black).display(), "Black wins!")
+    }
+
+    #[test]
+    fn test_legal_moves_are_cached_until_a_move() {
+        let mut game = test_traditional_game(Position::new_traditional());
+        assert_eq!(game.legal_move_computations, 0);
+
+        // Highlighting then clicking shares one computation
+        assert!(game.has_moves_from(&TileIndex::new(12)));
+        assert_eq!(game.query_tile(&TileIndex::new(12)), BitBoard::from_ints(vec![20, 28]));
+        assert_eq!(game.query_tile_detailed(&TileIndex::new(6)).len(), 2);
+        game.attempt_move_input(&TileIndex::new(12), &TileIndex::new(28), None).unwrap();
+        assert_eq!(game.legal_move_computations, 1);
+
+        // The move invalidates the cache, the next query recomputes it for the new position
+        assert_eq!(game.legal_moves, None);
+        assert_eq!(game.query_tile(&TileIndex::new(52)), BitBoard::from_ints(vec![44, 36]));
+        assert_eq!(game.query_tile(&TileIndex::new(12)), BitBoard::empty());
+        assert_eq!(game.legal_move_computations, 2);
+    }
+
+    #[test]
+    fn test_claim_draw() {
+        let mut game = test_traditional_game(Position::new_traditional());
+        assert!(!game.claim_draw());
+        std::sync::Arc::get_mut(&mut game.current_position.record).unwrap().fifty_move_counter = 100;
+        assert_eq!(game.can_claim_draw(), Some(DrawClaim::FiftyMoves));
+        assert!(game.claim_draw());
+        assert_eq!(game.game_over_state, Some(GameOver::Draw(DrawReason::FiftyMove)));
+    }
+
+    #[test]
+    fn test_captured_pieces() {
+        let mut game = test_traditional_game(Position::new_traditional());
+        assert_eq!(game.captured_pieces(), (vec![], vec![]));
+        // 1. e4 d5 2. exd5 Qxd5 3. Nc3 Qe5+ 4. Be2 Qxe2+ 5. Nxe2
+        for coordinate in ["e2e4", "d7d5", "e4d5", "d8d5", "b1c3", "d5e5", "f1e2", "e5e2", "c3e2"] {
+            let chess_move = coordinate_to_move(&mut game.current_position, coordinate, &game.engine.movegen).unwrap();
+            game.current_position.make_legal_move(&chess_move);
+        }
+        assert_eq!(
+            game.captured_pieces(),
+            (vec![PieceType::Pawn, PieceType::Bishop], vec![PieceType::Pawn, PieceType::Queen])
+        );
+        // En passant: 1. e4 a6 2. e5 d5 3. exd6
+        game.current_position = Position::new_traditional();
+        for coordinate in ["e2e4", "a7a6", "e4e5", "d7d5", "e5d6"] {
+            let chess_move = coordinate_to_move(&mut game.current_position, coordinate, &game.engine.movegen).unwrap();
+            game.current_position.make_legal_move(&chess_move);
+        }
+        assert_eq!(
+            game.captured_pieces(),
+            (vec![], vec![PieceType::Pawn])
+        )
+    }
+
+    #[test]
+    fn test_query_tile_detailed() {
+        let mut game = test_traditional_game(Position::from_string("4K2R15n36k3 w -".to_string()));
+        let detailed = game.query_tile_detailed(&TileIndex::new(7));
+        // The rook on h1 can take the knight on h3, while h2 is a quiet move
+        assert!(detailed.contains(&(TileIndex::new(23), MoveKind::Capture)));
+        assert!(detailed.contains(&(TileIndex::new(15), MoveKind::Quiet)));
+        assert!(!detailed.iter().any(|(tile, _)| tile == &TileIndex::new(31)));
+    }
+
+    #[test]
+    fn test_attempt_move_input_scored() {
+        let mut game = test_traditional_game(Position::from_string("4R2K54k1 w -".to_string()));
+        let feedback = game.attempt_move_input_scored(&TileIndex::new(4), &TileIndex::new(60), None).unwrap();
+        assert!(feedback.gives_check);
+        assert!(feedback.evaluation > 0); // White is up a rook
+        let feedback = game.attempt_move_input_scored(&TileIndex::new(62), &TileIndex::new(54), None).unwrap();
+        assert!(!feedback.gives_check);
+        assert_eq!(
+            game.attempt_move_input_scored(&TileIndex::new(54), &TileIndex::new(53), None),
+            Err(ChessError::InvalidMoveError) // Not black's turn
+        )
+    }
+
+    #[test]
+    fn test_underpromotion_input() {
+        let promote = |promotion: Option<PieceType>| {
+            let mut game = test_traditional_game(Position::from_string("4K47P10k w -".to_string()));
+            assert!(game.is_promotion_input(&TileIndex::new(52), &TileIndex::new(60)));
+            game.attempt_move_input(&TileIndex::new(52), &TileIndex::new(60), promotion).unwrap();
+            return game.current_position.pieces[0].get_piece_at(&TileIndex::new(60))
+        };
+        assert_eq!(
+            promote(Some(PieceType::Knight)),
+            Some(PieceType::Knight)
+        );
+        assert_eq!(
+            promote(None),
+            Some(PieceType::Queen)
+        )
+    }
+
+    #[test]
+    fn test_load_position_rejects_invalid() {
+        let mut game = test_cpu_game(Position::new_triangular());
+        // Two white kings
+        let result = game.load_position(Position::from_string("KK52k w -".to_string()));
+        assert_eq!(
+            result,
+            Err(vec![PositionError::WrongKingCount(Color::White)])
+        );
+        assert_eq!( // The current position is untouched
+            game.current_position.get_zobrist(),
+            Position::new_triangular().get_zobrist()
+        );
+        assert_eq!(
+            game.load_position(Position::from_string("1K8Q42k w -".to_string())),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn test_cpu_vs_cpu_terminates() {
+        let mut game = test_cpu_game(Position::from_string("1K8Q42k w -".to_string()));
+        while game.game_over_state == None && game.plies_played < 500 {
+            game.make_cpu_move();
+        }
+        assert_ne!(
+            game.game_over_state,
+            None
+        )
+    }
+
+    #[test]
+    fn test_full_cpu_game_reaches_game_over() {
+        let mut game = test_cpu_game(Position::new_triangular());
+        while game.game_over_state == None && game.plies_played < 500 {
+            game.make_cpu_move();
+        }
+        assert_ne!(
+            game.game_over_state,
+            None
+        );
+        assert!(game.plies_played < MAX_GAME_PLIES)
+    }
+}
@@ -0,0 +1,228 @@
+use std::sync::Arc;
+
+use lazy_static::lazy_static;
+
+use crate::bit_board::BitBoard;
+use crate::graph_boards::{graph_board::TileIndex, hexagonal_board::HexagonalBoardGraph, traditional_board::TraditionalBoardGraph, uniform_triangle_board::UniformTriangleBoardGraph};
+use crate::move_generator::MoveTables;
+use crate::piece_set::Color;
+use crate::position::Position;
+
+
+// Built on first use and shared by every searcher on the same board
+lazy_static! {
+    static ref TRADITIONAL_MOVE_TABLES: Arc<MoveTables> = Arc::new(BoardKind::Traditional.move_tables());
+    static ref HEXAGONAL_MOVE_TABLES: Arc<MoveTables> = Arc::new(BoardKind::Hexagonal.move_tables());
+    static ref TRIANGULAR_MOVE_TABLES: Arc<MoveTables> = Arc::new(BoardKind::Triangular.move_tables());
+}
+
+// Screen positions for every tile of a board, centered on the origin
+#[derive(Debug, Clone, PartialEq)]
+pub struct TileLayout {
+    pub spacing: f32, // Distance between orthogonal neighbors
+    pub positions: Vec<(f32, f32)> // Indexed by tile
+}
+
+// Identifies which board a Position or MoveTables belongs to
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BoardKind {
+    Traditional,
+    Hexagonal,
+    Triangular
+}
+
+impl BoardKind {
+    pub fn num_tiles(&self) -> usize {
+        return match self {
+            BoardKind::Traditional => 64,
+            BoardKind::Hexagonal => 91,
+            BoardKind::Triangular => 55
+        }
+    }
+
+    pub fn from_num_tiles(num_tiles: usize) -> Option<BoardKind> {
+        return match num_tiles {
+            64 => Some(BoardKind::Traditional),
+            91 => Some(BoardKind::Hexagonal),
+            55 => Some(BoardKind::Triangular),
+            _ => None
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<BoardKind> {
+        return match name {
+            "traditional" => Some(BoardKind::Traditional),
+            "hexagonal" => Some(BoardKind::Hexagonal),
+            "triangular" => Some(BoardKind::Triangular),
+            _ => None
+        }
+    }
+
+    pub fn num_edges(&self) -> usize {
+        return match self {
+            BoardKind::Traditional => TraditionalBoardGraph::new().0.edge_count(),
+            BoardKind::Hexagonal => HexagonalBoardGraph::new().0.edge_count(),
+            BoardKind::Triangular => UniformTriangleBoardGraph::new().0.edge_count()
+        }
+    }
+
+    pub fn mirror_tile(&self, tile: TileIndex) -> Option<TileIndex> {
+        // Reflects left to right, None on boards where that reflection hasn't been worked out
+        return match self {
+            BoardKind::Traditional => Some(TileIndex::new(tile.index() ^ 7)), // Swaps the a and h files
+            BoardKind::Hexagonal | BoardKind::Triangular => None
+        }
+    }
+
+    pub fn is_mirror_symmetric(&self) -> bool {
+        return self.mirror_tile(TileIndex::new(0)).is_some()
+    }
+
+    pub fn move_tables(&self) -> MoveTables {
+        return match self {
+            BoardKind::Traditional => TraditionalBoardGraph::new().0.move_tables(),
+            BoardKind::Hexagonal => HexagonalBoardGraph::new().0.move_tables(),
+            BoardKind::Triangular => UniformTriangleBoardGraph::new().0.move_tables()
+        }
+    }
+
+    pub fn shared_move_tables(&self) -> Arc<MoveTables> {
+        // Move tables are read-only, so one copy per board serves every Game and Searcher
+        return match self {
+            BoardKind::Traditional => TRADITIONAL_MOVE_TABLES.clone(),
+            BoardKind::Hexagonal => HEXAGONAL_MOVE_TABLES.clone(),
+            BoardKind::Triangular => TRIANGULAR_MOVE_TABLES.clone()
+        }
+    }
+
+    pub fn starting_fen(&self) -> &'static str {
+        // Pawns have to sit on the tiles the board graph marks as pawn_start, or double pushes break
+        return match self {
+            BoardKind::Traditional => "RNBQKBNRPPPPPPPP32pppppppprnbqkbnr w -",
+            BoardKind::Hexagonal => "BKNRP1QB2P2N1B1P3R3P4PPPPP21ppppp4p3r3p1b1n2p2bq1prnkb w -",
+            BoardKind::Triangular => "RKNP6QBP6NP6P17ppppnbnqkr w -"
+        }
+    }
+
+    pub fn pawn_start_tiles(&self, color: &Color) -> BitBoard {
+        return match self {
+            BoardKind::Traditional => TraditionalBoardGraph::new().0.pawn_start_tiles(color),
+            BoardKind::Hexagonal => HexagonalBoardGraph::new().0.pawn_start_tiles(color),
+            BoardKind::Triangular => UniformTriangleBoardGraph::new().0.pawn_start_tiles(color)
+        }
+    }
+
+    pub fn tile_coordinate(&self, tile: TileIndex) -> (f32, f32) {
+        // Where to draw a tile, in units of the distance between orthogonal neighbors
+        return match self {
+            BoardKind::Traditional => TraditionalBoardGraph::tile_coordinate(tile),
+            BoardKind::Hexagonal => HexagonalBoardGraph::tile_coordinate(tile),
+            BoardKind::Triangular => UniformTriangleBoardGraph::tile_coordinate(tile)
+        }
+    }
+
+    pub fn tile_layout(&self, board_size: f32) -> TileLayout {
+        // Scaled so the board, with half a tile of margin on each side, fits a square of board_size
+        let coordinates: Vec<(f32, f32)> = (0..self.num_tiles())
+            .map(|tile| self.tile_coordinate(TileIndex::new(tile)))
+            .collect();
+        let (mut min_x, mut max_x, mut min_y, mut max_y) = (f32::MAX, f32::MIN, f32::MAX, f32::MIN);
+        for (x, y) in &coordinates {
+            min_x = min_x.min(*x);
+            max_x = max_x.max(*x);
+            min_y = min_y.min(*y);
+            max_y = max_y.max(*y);
+        }
+        let spacing = board_size / ((max_x - min_x).max(max_y - min_y) + 1.0);
+        let (center_x, center_y) = ((min_x + max_x) / 2.0, (min_y + max_y) / 2.0);
+        let positions = coordinates.into_iter()
+            .map(|(x, y)| ((x - center_x) * spacing, (y - center_y) * spacing))
+            .collect();
+        return TileLayout { spacing, positions }
+    }
+
+    pub fn initial_position(&self) -> Position {
+        return match self {
+            BoardKind::Traditional => Position::new_traditional(),
+            BoardKind::Hexagonal => Position::new_hexagonal(),
+            BoardKind::Triangular => Position::new_triangular()
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::piece_set::PieceType;
+
+    #[test]
+    fn test_num_tiles_matches_move_tables() {
+        for board_kind in [BoardKind::Traditional, BoardKind::Hexagonal, BoardKind::Triangular] {
+            assert_eq!(
+                board_kind.move_tables().king_table.num_tiles(),
+                board_kind.num_tiles()
+            )
+        }
+    }
+
+    #[test]
+    fn test_board_names() {
+        for board_kind in [BoardKind::Traditional, BoardKind::Hexagonal, BoardKind::Triangular] {
+            let name = format!("{:?}", board_kind).to_lowercase();
+            assert_eq!(BoardKind::from_name(&name), Some(board_kind))
+        }
+        assert_eq!(BoardKind::from_name("aperiodic"), None)
+    }
+
+    #[test]
+    fn test_hexagonal_tile_layout() {
+        let layout = BoardKind::Hexagonal.tile_layout(680.0);
+        assert_eq!(
+            layout.positions.len(),
+            HexagonalBoardGraph::new().0.node_count()
+        );
+        assert_eq!(layout.positions.len(), 91);
+        // The middle tile sits at the center and every tile fits inside the board
+        let (center_x, center_y) = layout.positions[45];
+        assert!(center_x.abs() < 1e-3 && center_y.abs() < 1e-3);
+        for (x, y) in &layout.positions {
+            assert!(x.abs() + layout.spacing / 2.0 <= 340.0 + 1e-3 && y.abs() + layout.spacing / 2.0 <= 340.0 + 1e-3)
+        }
+    }
+
+    #[test]
+    fn test_traditional_tile_layout() {
+        // Matches the old fixed grid of 85 pixel squares
+        let layout = BoardKind::Traditional.tile_layout(680.0);
+        assert_eq!(layout.spacing, 85.0);
+        assert_eq!(layout.positions[0], (-297.5, -297.5));
+        assert_eq!(layout.positions[63], (297.5, 297.5))
+    }
+
+    #[test]
+    fn test_initial_position_fits_board() {
+        for board_kind in [BoardKind::Traditional, BoardKind::Hexagonal, BoardKind::Triangular] {
+            let position = board_kind.initial_position();
+            let occupied = position.pieces[0].occupied | position.pieces[1].occupied;
+            assert_eq!(
+                occupied & !BitBoard::new((1 << board_kind.num_tiles()) - 1),
+                BitBoard::empty()
+            )
+        }
+    }
+
+    #[test]
+    fn test_starting_pawns_on_pawn_start_tiles() {
+        for board_kind in [BoardKind::Traditional, BoardKind::Hexagonal, BoardKind::Triangular] {
+            let position = board_kind.initial_position();
+            for color in [Color::White, Color::Black] {
+                assert_eq!(
+                    position.pieces[color.as_idx()].piece_boards[PieceType::Pawn.as_idx()],
+                    board_kind.pawn_start_tiles(&color),
+                    "{:?} {}", board_kind, color
+                )
+            }
+        }
+    }
+}
@@ -1,10 +1,10 @@
-use bevy::ecs::component::Component;
 use petgraph::graph::{Graph, NodeIndex};
 use petgraph::visit::EdgeRef;
 use std::collections::{HashSet, HashMap};
 use std::ops::{Deref, DerefMut};
+use serde::{Deserialize, Serialize};
 
-use crate::bit_board::{BitBoard, CarryRippler};
+use crate::bit_board::{BitBoard, BitBoardTiles, CarryRippler};
 use crate::limited_int::LimitedInt;
 use crate::move_generator::MoveTables;
 use crate::piece_set::{Color, Piece};
@@ -13,7 +13,8 @@ use crate::movement_tables::{JumpTable, DirectionalSlideTable, SlideTables, Pawn
 
 pub type TileIndex = NodeIndex;
 
-#[derive(Debug, Clone, Copy, Component)]
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[cfg_attr(feature = "gui", derive(bevy::ecs::component::Component))]
 pub struct Tile<const N: u8> {
     pub id: TileIndex,
     pub occupant: Option<Piece>,
@@ -22,13 +23,32 @@ pub struct Tile<const N: u8> {
 }
 
 // Generic graph that uses LimitedIntTrait for the edges
-#[derive(Debug)]
+// Serializable so a slow-to-build board can be saved once and loaded after
+#[derive(Debug, Serialize, Deserialize)]
 pub struct GraphBoard<const N: u8, const E: u8>(Graph<Tile<N>, LimitedInt<E>>);
 
 impl <const N: u8, const E: u8> GraphBoard<N, E> {
     pub fn new() -> Self {
         GraphBoard(Graph::new())
     }
+
+    pub fn from_edge_spec(
+        num_tiles: usize,
+        tile_fn: impl Fn(TileIndex) -> Tile<N>,
+        edge_fn: impl Fn(TileIndex) -> Vec<(LimitedInt<E>, TileIndex)>
+    ) -> Self {
+        // A board is just its tiles and, for each tile, the direction to each neighbor
+        let mut board_graph = GraphBoard::new();
+        for tile in 0..num_tiles {
+            board_graph.add_node(tile_fn(TileIndex::new(tile)));
+        }
+        for tile_idx in board_graph.node_indices() {
+            for (direction, other_idx) in edge_fn(tile_idx) {
+                board_graph.add_edge(tile_idx, other_idx, direction);
+            }
+        }
+        return board_graph
+    }
    
     fn get_next_tile_in_direction(&self, source_tile: TileIndex, direction: &LimitedInt<E>) -> Option<TileIndex> {
         self.edges_directed(source_tile, petgraph::Direction::Outgoing)
@@ -36,13 +56,13 @@ impl <const N: u8, const E: u8> GraphBoard<N, E> {
             .map(|edge| edge.target())
     }
    
-    pub fn knight_jumps_from(&self, source_tile: TileIndex) -> HashSet<TileIndex> {
-        let mut result: HashSet<TileIndex> = HashSet::new();
-        for direction in LimitedInt::<E>::all_values() {
+    pub fn knight_jumps_from(&self, source_tile: TileIndex) -> BitBoard {
+        let mut result = BitBoard::empty();
+        for &direction in LimitedInt::<E>::values() {
             if let Some(next_tile) = self.get_next_tile_in_direction(source_tile, &direction) {
                 for next_direction in LimitedInt::<E>::adjacent_values(&direction) {
                     if let Some(final_tile) = self.get_next_tile_in_direction(next_tile, &next_direction) {
-                        result.insert(final_tile);
+                        result |= BitBoard::new(1 << final_tile.index());
                     }
                 }
             }
@@ -50,8 +70,17 @@ impl <const N: u8, const E: u8> GraphBoard<N, E> {
         return result
     }
 
-    pub fn slides_from_in_direction(&self, source_tile: TileIndex, direction: &LimitedInt<E>, limit: u32, obstructions: BitBoard) -> HashSet<TileIndex> {
-        let mut result: HashSet<TileIndex> = HashSet::new();
+    pub fn slides_from_in_direction(&self, source_tile: TileIndex, direction: &LimitedInt<E>, limit: u32, obstructions: BitBoard) -> BitBoard {
+        let mut result = BitBoard::empty();
+        for tile in self.ordered_slides_from_in_direction(source_tile, direction, limit, obstructions) {
+            result |= BitBoard::new(1 << tile.index());
+        }
+        return result
+    }
+
+    pub fn ordered_slides_from_in_direction(&self, source_tile: TileIndex, direction: &LimitedInt<E>, limit: u32, obstructions: BitBoard) -> Vec<TileIndex> {
+        // Nearest tile first, so if an obstruction was hit it is always the last tile
+        let mut result: Vec<TileIndex> = vec![];
         let mut current_tile = source_tile;
         let mut distance_traveled = 0;
         let mut hit_obstruction = false;
@@ -60,7 +89,7 @@ impl <const N: u8, const E: u8> GraphBoard<N, E> {
             if BitBoard::new(1 << n.index()) & obstructions != BitBoard::empty() {
                 hit_obstruction = true;
             } // Assuming the first obstruction is an enemy, include it in result
-            result.insert(n);
+            result.push(n);
             distance_traveled += 1;
             if (distance_traveled == limit) | hit_obstruction {
                 break
@@ -70,6 +99,10 @@ impl <const N: u8, const E: u8> GraphBoard<N, E> {
         return result
     }
 
+    pub fn ray(&self, source_tile: TileIndex, direction: &LimitedInt<E>, limit: u32) -> Vec<TileIndex> {
+        return self.ordered_slides_from_in_direction(source_tile, direction, limit, BitBoard::empty())
+    }
+
     pub fn cast_slides_from(
         &self,
         source_tile: TileIndex,
@@ -89,16 +122,16 @@ impl <const N: u8, const E: u8> GraphBoard<N, E> {
         };
 
         let mut result: HashSet<TileIndex> = HashSet::new();
-        for direction in LimitedInt::<E>::all_values()
-                                    .into_iter()
+        for &direction in LimitedInt::<E>::values()
+                                    .iter()
                                     .skip(initital_direction)
                                     .step_by(direction_step) {
-            result.extend(self.slides_from_in_direction(
+            result.extend(BitBoardTiles::new(self.slides_from_in_direction(
                 source_tile,
                 &direction,
                 limit,
                 obstructions
-            ))
+            )))
         }
         return result
     }
@@ -106,7 +139,7 @@ impl <const N: u8, const E: u8> GraphBoard<N, E> {
     pub fn knight_jumps_table(&self) -> JumpTable {
         let mut result: Vec<BitBoard> = vec![];
         for source_tile in self.0.node_indices() {
-            result.push(BitBoard::from_tile_indices(self.knight_jumps_from(source_tile)))
+            result.push(self.knight_jumps_from(source_tile))
         }
         return JumpTable::new(result)
     }
@@ -114,26 +147,21 @@ impl <const N: u8, const E: u8> GraphBoard<N, E> {
     pub fn slide_table_for_direction(&self, direction: &LimitedInt<E>) -> DirectionalSlideTable {
         let mut attack_table: Vec<HashMap<BitBoard, BitBoard>> = vec![];
         for source_tile in self.0.node_indices() {
-            let unobstructed_attacks = BitBoard::from_tile_indices(
-                self.slides_from_in_direction(
-                    source_tile,
-                    direction,
-                    0,
-                    BitBoard::empty()
-                )
+            let unobstructed_attacks = self.slides_from_in_direction(
+                source_tile,
+                direction,
+                0,
+                BitBoard::empty()
             );
             let mut attack_map = HashMap::new();
-            attack_map.insert(BitBoard::empty(), unobstructed_attacks);
-            for subset in CarryRippler::new(unobstructed_attacks) {
+            for subset in CarryRippler::with_empty(unobstructed_attacks) {
                 attack_map.insert(
                     subset,
-                    BitBoard::from_tile_indices(
-                        self.slides_from_in_direction(
-                            source_tile,
-                            direction,
-                            0,
-                            subset
-                        )
+                    self.slides_from_in_direction(
+                        source_tile,
+                        direction,
+                        0,
+                        subset
                     )
                 );
             }
@@ -144,7 +172,7 @@ impl <const N: u8, const E: u8> GraphBoard<N, E> {
 
     pub fn all_slide_tables(&self) -> SlideTables {
         let mut output = vec![];
-        for direction in LimitedInt::<E>::all_values() {
+        for direction in LimitedInt::<E>::values() {
             output.push(self.slide_table_for_direction(&direction))
         }
         return SlideTables::new(output)
@@ -164,27 +192,28 @@ impl <const N: u8, const E: u8> GraphBoard<N, E> {
         return JumpTable::new(result)
     }
 
-    pub fn pawn_single_table(&self, color: &Color) -> JumpTable {
-        let mut result: Vec<BitBoard> = vec![];
-
+    pub fn pawn_forward_direction(&self, source_tile: TileIndex, color: &Color) -> LimitedInt<E> {
+        // Rotated tiles (orientation != 0) turn White's forward direction 0 by the same angle
         let forward_or_backward = match color {
             Color::White => 0,
             _ => E / 2 // This assumes max_value is even
         };
-
         let map = LimitedInt::<N>::map_to_other::<E>();
+        return map.get(&self.0[source_tile].orientation).unwrap().shift_by(forward_or_backward)
+    }
 
-        for source_tile in self.0.node_indices() {
-            let tile = &self.0[source_tile];
+    pub fn pawn_single_table(&self, color: &Color) -> JumpTable {
+        let mut result: Vec<BitBoard> = vec![];
 
-            let direction = map.get(&tile.orientation).unwrap().shift_by(forward_or_backward);
+        for source_tile in self.0.node_indices() {
+            let direction = self.pawn_forward_direction(source_tile, color);
 
-            result.push(BitBoard::from_tile_indices(self.slides_from_in_direction(
+            result.push(self.slides_from_in_direction(
                 source_tile,
                 &direction,
                 1,
                 BitBoard::empty(),
-            )));
+            ));
         }
         return JumpTable::new(result)
     }
@@ -192,27 +221,18 @@ impl <const N: u8, const E: u8> GraphBoard<N, E> {
     pub fn pawn_attack_table(&self, color: &Color) -> JumpTable {
         let mut result: Vec<BitBoard> = vec![];
 
-        let forward_or_backward = match color {
-            Color::White => 0,
-            _ => E / 2 // This assumes max_value is even
-        };
-
-        let map = LimitedInt::<N>::map_to_other::<E>();
-
         for source_tile in self.0.node_indices() {
-            let tile = &self.0[source_tile];
-
-            let move_direction = map.get(&tile.orientation).unwrap().shift_by(forward_or_backward);
+            let move_direction = self.pawn_forward_direction(source_tile, color);
             let attack_directions = LimitedInt::<E>::adjacent_values(&move_direction);
             let mut attacks = BitBoard::empty();
 
             for direction in attack_directions {
-                attacks |= BitBoard::from_tile_indices(self.slides_from_in_direction(
+                attacks |= self.slides_from_in_direction(
                     source_tile,
                     &direction,
                     1, 
                     BitBoard::empty()
-                ))
+                )
             }
             result.push(attacks);
         }
@@ -235,12 +255,15 @@ impl <const N: u8, const E: u8> GraphBoard<N, E> {
                 _ => BitBoard::empty()
             };
 
+            // Any piece on the intermediate tile blocks the double move
             let mut attack_map = HashMap::new();
-            attack_map.insert(BitBoard::empty(), unobstructed_attacks);
-
-            let occupied = single_table[source_tile];
-            attack_map.insert(occupied, BitBoard::empty());
-        
+            for occupied in CarryRippler::with_empty(single_table[source_tile]) {
+                let attacks = match occupied.is_zero() {
+                    true => unobstructed_attacks,
+                    false => BitBoard::empty()
+                };
+                attack_map.insert(occupied, attacks);
+            }
             attack_table.push(attack_map);
         }
         return DirectionalSlideTable::new(attack_table)
@@ -267,6 +290,56 @@ impl <const N: u8, const E: u8> GraphBoard<N, E> {
             reverse_black_pawn_table: self.pawn_attack_table(&Color::Black).reverse()
         }
     }
+
+    pub fn pawn_start_tiles(&self, color: &Color) -> BitBoard {
+        let mut output = BitBoard::empty();
+        for tile_idx in self.node_indices() {
+            if self[tile_idx].pawn_start.as_ref() == Some(color) {
+                output.flip_bit_at_tile_index(tile_idx);
+            }
+        }
+        return output
+    }
+
+    pub fn mirror_map(&self) -> Vec<TileIndex> {
+        // Reflects each tile along its forward line (the line a pawn would travel),
+        // so the tiles behind it are swapped with the tiles ahead of it
+        let mut output = vec![];
+        for source_tile in self.node_indices() {
+            let forward = self.pawn_forward_direction(source_tile, &Color::White);
+            let backward = self.pawn_forward_direction(source_tile, &Color::Black);
+            let tiles_ahead = self.slides_from_in_direction(source_tile, &forward, 0, BitBoard::empty()).0.count_ones() as usize;
+            let tiles_behind = self.slides_from_in_direction(source_tile, &backward, 0, BitBoard::empty()).0.count_ones() as usize;
+            let (direction, num_steps) = match tiles_ahead >= tiles_behind {
+                true => (forward, tiles_ahead - tiles_behind),
+                false => (backward, tiles_behind - tiles_ahead)
+            };
+            let mut mirrored_tile = source_tile;
+            for _ in 0..num_steps {
+                mirrored_tile = self.get_next_tile_in_direction(mirrored_tile, &direction).unwrap();
+            }
+            output.push(mirrored_tile);
+        }
+        output
+    }
+
+    pub fn to_dot(&self) -> String {
+        // GraphViz output for debugging board topology, edges are labeled by direction
+        let mut output = "digraph {\n".to_string();
+        for tile in self.node_indices() {
+            output.push_str(&format!("    {} [label=\"{}\"];\n", tile.index(), tile.index()));
+        }
+        for edge in self.edge_references() {
+            output.push_str(&format!(
+                "    {} -> {} [label=\"{}\"];\n",
+                edge.source().index(),
+                edge.target().index(),
+                edge.weight().0
+            ));
+        }
+        output.push('}');
+        output
+    }
 }
 
 impl<const N: u8, const E: u8> Deref for GraphBoard<N, E> {
@@ -291,6 +364,8 @@ pub type UniformTileOrientation = LimitedInt<1>;
 mod tests {
     use super::*;
     use crate::graph_boards::traditional_board::{TraditionalBoardGraph, TraditionalDirection};
+    use crate::graph_boards::hexagonal_board::HexagonalBoardGraph;
+    use crate::graph_boards::uniform_triangle_board::UniformTriangleBoardGraph;
 
     fn test_traditional_board() -> TraditionalBoardGraph {
         return TraditionalBoardGraph::new();
@@ -320,7 +395,7 @@ mod tests {
         let source_tile = TileIndex::new(27);
         assert_eq!(
             board.0.knight_jumps_from(source_tile),
-            HashSet::from_iter([
+            BitBoard::from_tile_indices(HashSet::from_iter([
                 TileIndex::new(27 + 10),
                 TileIndex::new(27 - 10),
                 TileIndex::new(27 + 6),
@@ -329,7 +404,76 @@ mod tests {
                 TileIndex::new(27 - 17),
                 TileIndex::new(27 + 15),
                 TileIndex::new(27 - 15)
-            ])
+            ]))
+        )
+    }
+
+    fn hashset_knight_jumps<const N: u8, const E: u8>(board: &GraphBoard<N, E>, source_tile: TileIndex) -> HashSet<TileIndex> {
+        // The original HashSet construction, kept as a reference for the BitBoard one
+        let mut result: HashSet<TileIndex> = HashSet::new();
+        for &direction in LimitedInt::<E>::values() {
+            if let Some(next_tile) = board.get_next_tile_in_direction(source_tile, &direction) {
+                for next_direction in LimitedInt::<E>::adjacent_values(&direction) {
+                    if let Some(final_tile) = board.get_next_tile_in_direction(next_tile, &next_direction) {
+                        result.insert(final_tile);
+                    }
+                }
+            }
+        }
+        return result
+    }
+
+    #[test]
+    fn test_knight_jumps_match_hashset_construction() {
+        let traditional = test_traditional_board();
+        for tile in [0, 7, 27, 49, 63] {
+            assert_eq!(
+                traditional.0.knight_jumps_from(TileIndex::new(tile)),
+                BitBoard::from_tile_indices(hashset_knight_jumps(&traditional.0, TileIndex::new(tile)))
+            )
+        }
+        let hexagonal = HexagonalBoardGraph::new();
+        for tile in [0, 30, 45, 90] {
+            assert_eq!(
+                hexagonal.0.knight_jumps_from(TileIndex::new(tile)),
+                BitBoard::from_tile_indices(hashset_knight_jumps(&hexagonal.0, TileIndex::new(tile)))
+            )
+        }
+    }
+
+    #[test]
+    fn test_ray_is_ordered() {
+        let board = test_traditional_board();
+        assert_eq!(
+            board.0.ray(TileIndex::new(0), &TraditionalDirection::new(0), 0),
+            [8, 16, 24, 32, 40, 48, 56].map(TileIndex::new).to_vec()
+        );
+        assert_eq!(
+            board.0.ray(TileIndex::new(0), &TraditionalDirection::new(0), 2),
+            vec![TileIndex::new(8), TileIndex::new(16)]
+        );
+        assert_eq!(
+            board.0.ray(TileIndex::new(0), &TraditionalDirection::new(2), 0),
+            vec![]
+        )
+    }
+
+    #[test]
+    fn test_ordered_slides_end_at_first_obstruction() {
+        let board = test_traditional_board();
+        let obstructions = BitBoard::from_ints(vec![24, 40]);
+        let slides = board.0.ordered_slides_from_in_direction(TileIndex::new(0), &TraditionalDirection::new(0), 0, obstructions);
+        assert_eq!(
+            slides,
+            vec![TileIndex::new(8), TileIndex::new(16), TileIndex::new(24)]
+        );
+        assert_eq!( // The first blocker, not the farther one on 40
+            slides.last(),
+            Some(&TileIndex::new(24))
+        );
+        assert_eq!(
+            BitBoard::from_tile_indices(HashSet::from_iter(slides)),
+            board.0.slides_from_in_direction(TileIndex::new(0), &TraditionalDirection::new(0), 0, obstructions)
         )
     }
 
@@ -339,14 +483,14 @@ mod tests {
         let source_tile = TileIndex::new(1);
         assert_eq!(
             board.0.slides_from_in_direction(source_tile, &TraditionalDirection::new(6), 0, BitBoard::empty()),
-            HashSet::from_iter([
+            BitBoard::from_tile_indices(HashSet::from_iter([
                 TileIndex::new(2),
                 TileIndex::new(3),
                 TileIndex::new(4),
                 TileIndex::new(5),
                 TileIndex::new(6),
                 TileIndex::new(7),
-            ])
+            ]))
         )
     }
     #[test]
@@ -355,7 +499,7 @@ mod tests {
         let source_tile = TileIndex::new(1);
         assert_eq!(
             board.0.slides_from_in_direction(source_tile, &TraditionalDirection::new(6), 1, BitBoard::empty()),
-            HashSet::from_iter([TileIndex::new(2)])
+            BitBoard::from_tile_indices(HashSet::from_iter([TileIndex::new(2)]))
         )
     }
 
@@ -366,11 +510,11 @@ mod tests {
         let obstructions = BitBoard::new(16);
         assert_eq!(
             board.0.slides_from_in_direction(source_tile, &TraditionalDirection::new(6), 0, obstructions),
-            HashSet::from_iter([
+            BitBoard::from_tile_indices(HashSet::from_iter([
                 TileIndex::new(2),
                 TileIndex::new(3),
                 TileIndex::new(4),
-            ])
+            ]))
         )
     }
 
@@ -497,4 +641,161 @@ mod tests {
             ])
         )
     }
+
+    #[test]
+    fn test_mirror_map() {
+        let board = test_traditional_board();
+        let mirror_map = board.0.mirror_map();
+        assert_eq!(
+            mirror_map[0],
+            TileIndex::new(56)
+        );
+        assert_eq!(
+            mirror_map[56],
+            TileIndex::new(0)
+        );
+        assert_eq!(
+            mirror_map[12],
+            TileIndex::new(52)
+        );
+        for tile in board.0.node_indices() {
+            assert_eq!( // Mirroring twice returns the original tile
+                mirror_map[mirror_map[tile.index()].index()],
+                tile
+            )
+        }
+    }
+
+    #[test]
+    fn test_mirror_map_is_involution_on_other_boards() {
+        // Only holds if every edge has a matching edge in the opposite direction
+        let hexagonal_map = HexagonalBoardGraph::new().0.mirror_map();
+        for tile in 0..hexagonal_map.len() {
+            assert_eq!(
+                hexagonal_map[hexagonal_map[tile].index()],
+                TileIndex::new(tile)
+            )
+        }
+        let triangular_map = UniformTriangleBoardGraph::new().0.mirror_map();
+        for tile in 0..triangular_map.len() {
+            assert_eq!(
+                triangular_map[triangular_map[tile].index()],
+                TileIndex::new(tile)
+            )
+        }
+        assert_eq!( // White's king on the triangular board is mirrored onto Black's king
+            triangular_map[1],
+            TileIndex::new(53)
+        )
+    }
+
+    fn rotated_star_board(orientation: u8) -> GraphBoard<6, 10> {
+        // Tile 0 has the given orientation and one neighbor in each direction (tile d + 1 for direction d)
+        let mut board = GraphBoard::<6, 10>::new();
+        for tile in 0..11 {
+            board.add_node(Tile {
+                id: TileIndex::new(tile),
+                occupant: None,
+                orientation: LimitedInt::<6>::new(if tile == 0 { orientation } else { 0 }),
+                pawn_start: None
+            });
+        }
+        for direction in LimitedInt::<10>::all_values() {
+            board.add_edge(TileIndex::new(0), TileIndex::new(direction.0 as usize + 1), direction);
+        }
+        board
+    }
+
+    #[test]
+    fn test_pawn_forward_direction_for_each_orientation() {
+        // Orientation o is rotated by -60 * o degrees, directions are 36 degrees apart,
+        // so these are the directions closest to each rotated forward
+        let geometric_forward = [0, 8, 7, 5, 3, 2];
+        for orientation in 0..6 {
+            let board = rotated_star_board(orientation);
+            let forward = geometric_forward[orientation as usize];
+            assert_eq!(
+                board.pawn_forward_direction(TileIndex::new(0), &Color::White),
+                LimitedInt::<10>::new(forward)
+            );
+            assert_eq!(
+                board.pawn_single_table(&Color::White)[TileIndex::new(0)],
+                BitBoard::from_ints(vec![forward as u128 + 1])
+            );
+            assert_eq!( // Black moves the opposite way
+                board.pawn_single_table(&Color::Black)[TileIndex::new(0)],
+                BitBoard::from_ints(vec![(forward as u128 + 5) % 10 + 1])
+            );
+            assert_eq!( // Attacks are the two directions next to forward
+                board.pawn_attack_table(&Color::White)[TileIndex::new(0)],
+                BitBoard::from_ints(vec![(forward as u128 + 9) % 10 + 1, (forward as u128 + 1) % 10 + 1])
+            );
+        }
+    }
+
+    #[test]
+    fn test_to_dot() {
+        let board = test_traditional_board();
+        let dot = board.0.to_dot();
+        assert_eq!(
+            dot.lines().filter(|line| line.contains("[label=") && !line.contains("->")).count(),
+            64
+        );
+        assert!(dot.contains("    0 -> 8 [label=\"0\"];"))
+    }
+
+    fn edge_set<const N: u8, const E: u8>(board: &GraphBoard<N, E>) -> HashSet<(usize, usize, u8)> {
+        return board.edge_references()
+            .map(|edge| (edge.source().index(), edge.target().index(), edge.weight().0))
+            .collect()
+    }
+
+    #[test]
+    fn test_from_edge_spec_matches_hand_written_traditional() {
+        // Directions 0..8 run counter-clockwise from north as (file, rank) steps
+        let steps = [(0, 1), (-1, 1), (-1, 0), (-1, -1), (0, -1), (1, -1), (1, 0), (1, 1)];
+        let mut hand_written = GraphBoard::<1, 8>::new();
+        for tile in 0..64 {
+            let pawn_start = match tile / 8 {
+                1 => Some(Color::White),
+                6 => Some(Color::Black),
+                _ => None
+            };
+            hand_written.add_node(Tile {
+                id: TileIndex::new(tile),
+                occupant: None,
+                orientation: LimitedInt::new(0),
+                pawn_start
+            });
+        }
+        for tile in 0..64 {
+            let (file, rank) = ((tile % 8) as i32, (tile / 8) as i32);
+            for (direction, (file_step, rank_step)) in steps.iter().enumerate() {
+                let (other_file, other_rank) = (file + file_step, rank + rank_step);
+                if (0..8).contains(&other_file) && (0..8).contains(&other_rank) {
+                    hand_written.add_edge(
+                        TileIndex::new(tile),
+                        TileIndex::new((other_file + 8 * other_rank) as usize),
+                        TraditionalDirection::new(direction as u8)
+                    );
+                }
+            }
+        }
+
+        let generic = test_traditional_board().0;
+        assert_eq!(
+            generic.node_count(),
+            hand_written.node_count()
+        );
+        for tile_idx in hand_written.node_indices() {
+            assert_eq!(
+                generic[tile_idx].pawn_start,
+                hand_written[tile_idx].pawn_start
+            );
+        }
+        assert_eq!(
+            edge_set(&generic),
+            edge_set(&hand_written)
+        )
+    }
 }
@@ -16,17 +16,17 @@ pub struct HexagonalBoardGraph(pub GraphBoard<1, 12>);
 
 impl HexagonalBoardGraph {
     pub fn new() -> Self {
-        let mut board_graph = GraphBoard::new();
-        for tile in 0..91 {
-            board_graph.add_node(Self::new_tile(TileIndex::new(tile)));
-        }
-        for tile_idx in board_graph.node_indices() {
-            for direction in Self::get_valid_directions(tile_idx) {
+        return HexagonalBoardGraph(GraphBoard::from_edge_spec(91, Self::new_tile, Self::get_edges))
+    }
+
+    fn get_edges(tile_idx: TileIndex) -> Vec<(HexagonalDirection, TileIndex)> {
+        return Self::get_valid_directions(tile_idx)
+            .into_iter()
+            .map(|direction| {
                 let other_idx = TileIndex::from((tile_idx.index() as i32 + Self::get_tile_index_shift(tile_idx, &direction)) as u32);
-                board_graph.add_edge(tile_idx, other_idx, direction);
-            }
-        }
-        return HexagonalBoardGraph(board_graph)
+                (direction, other_idx)
+            })
+            .collect()
     }
 
     fn row_length(n: TileIndex) -> i32 {
@@ -41,6 +41,25 @@ impl HexagonalBoardGraph {
         }
     }
 
+    pub fn axial_coordinate(tile: TileIndex) -> (i32, i32) {
+        // r is the row in index order, q runs along it and is shifted past the middle row so each neighbor is one step away
+        // Orthogonal neighbors are then (q ± 1, r), (q, r ± 1) and (q ± 1, r ± 1)
+        let mut row_start = 0;
+        let mut row = 0;
+        while tile.index() as i32 >= row_start + Self::row_length(TileIndex::new(row_start as usize)) {
+            row_start += Self::row_length(TileIndex::new(row_start as usize));
+            row += 1;
+        }
+        let offset = tile.index() as i32 - row_start;
+        return (offset + (row - 5).max(0), row)
+    }
+
+    pub fn tile_coordinate(tile: TileIndex) -> (f32, f32) {
+        // Orthogonal neighbors are one unit apart and White's forward direction points up
+        let (q, r) = Self::axial_coordinate(tile);
+        return (3.0_f32.sqrt() / 2.0 * (q - r) as f32, (q + r) as f32 / 2.0)
+    }
+
     fn new_tile(source_tile: TileIndex) -> Tile<1> {
         let pawn_start = match source_tile.index() {
             4 | 10 | 17 | 25 | 30..=34 => Some(Color::White),
@@ -132,11 +151,11 @@ impl HexagonalBoardGraph {
         let row = Self::row_length(source_tile);
         return match direction.0 {
             0 => {
-                if source_tile.index() <= 40 { row + 1 }
+                if source_tile.index() < 40 { row + 1 }
                 else { row }
             },
             1 => {
-                if source_tile.index() <= 30 { 2 * row + 2 }
+                if source_tile.index() < 30 { 2 * row + 2 }
                 else if source_tile.index() >= 41 { 2 * row - 2 }
                 else { 2 * row + 1 }
             },
@@ -150,32 +169,73 @@ impl HexagonalBoardGraph {
             },
             4 => -1,
             5 => {
-                if source_tile.index() <= 51 { -row - 1 }
+                if source_tile.index() <= 50 { -row - 1 }
                 else { -row - 2 }
             },
             6 => {
-                if source_tile.index() <= 51 { -row }
+                if source_tile.index() <= 50 { -row }
                 else { -row - 1}
             },
             7 => {
-                if source_tile.index() >= 62 { -2 * row - 2 }
-                else if source_tile.index() <= 41 { -2 * row + 2 }
+                if source_tile.index() >= 61 { -2 * row - 2 }
+                else if source_tile.index() <= 50 { -2 * row + 2 }
                 else { -2 * row - 1 }
             },
             8 => {
-                if source_tile.index() <= 51 { -row + 1 }
+                if source_tile.index() <= 50 { -row + 1 }
                 else { -row }
             },
             9 => {
-                if source_tile.index() <= 51 { -row + 2 }
+                if source_tile.index() <= 50 { -row + 2 }
                 else { -row + 1 }
             },
             10 => 1,
             11 => {
-                if source_tile.index() <= 40 { row + 2 }
+                if source_tile.index() < 40 { row + 2 }
                 else { row + 1 }
             },
             _ => 0
         }
     }
 }
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bit_board::BitBoard;
+
+    #[test]
+    fn test_axial_coordinates() {
+        assert_eq!(HexagonalBoardGraph::axial_coordinate(TileIndex::new(0)), (0, 0));
+        assert_eq!(HexagonalBoardGraph::axial_coordinate(TileIndex::new(45)), (5, 5));
+        assert_eq!(HexagonalBoardGraph::axial_coordinate(TileIndex::new(51)), (1, 6));
+        assert_eq!(HexagonalBoardGraph::axial_coordinate(TileIndex::new(90)), (10, 10))
+    }
+
+    #[test]
+    fn test_neighbors_are_evenly_spaced() {
+        // Orthogonal neighbors are 1 apart, diagonal ones sqrt(3)
+        let board = HexagonalBoardGraph::new();
+        for tile in 0..91 {
+            let (x, y) = HexagonalBoardGraph::tile_coordinate(TileIndex::new(tile));
+            for direction in HexagonalDirection::all_values() {
+                let neighbor = board.0.slides_from_in_direction(TileIndex::new(tile), &direction, 1, BitBoard::empty());
+                if let Some(neighbor) = neighbor.lowest_one() {
+                    let (neighbor_x, neighbor_y) = HexagonalBoardGraph::tile_coordinate(neighbor);
+                    let expected = match direction.0 % 2 {
+                        0 => 1.0,
+                        _ => 3.0_f32.sqrt()
+                    };
+                    assert!(((neighbor_x - x).hypot(neighbor_y - y) - expected).abs() < 1e-4)
+                }
+            }
+        }
+        // White's forward direction is straight up
+        let forward = board.0.slides_from_in_direction(TileIndex::new(0), &HexagonalDirection::new(0), 1, BitBoard::empty());
+        assert_eq!(
+            HexagonalBoardGraph::tile_coordinate(forward.lowest_one().unwrap()),
+            (0.0, 1.0)
+        )
+    }
+}
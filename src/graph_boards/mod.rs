@@ -1,3 +1,4 @@
+pub mod board_kind;
 pub mod graph_board;
 pub mod traditional_board;
 pub mod hexagonal_board;
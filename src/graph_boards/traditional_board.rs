@@ -18,17 +18,38 @@ pub struct TraditionalBoardGraph(pub GraphBoard<1, 8>);
 
 impl TraditionalBoardGraph {
     pub fn new() -> Self {
-        let mut board_graph = GraphBoard::new();
-        for tile in 0..64 {
-            board_graph.add_node(Self::new_tile(TileIndex::new(tile)));
-        }
-        for tile_idx in board_graph.node_indices() {
-            for direction in Self::get_valid_directions(tile_idx) {
+        return TraditionalBoardGraph(GraphBoard::from_edge_spec(64, Self::new_tile, Self::get_edges))
+    }
+
+    fn get_edges(tile_idx: TileIndex) -> Vec<(TraditionalDirection, TileIndex)> {
+        return Self::get_valid_directions(tile_idx)
+            .into_iter()
+            .map(|direction| {
                 let other_idx = TileIndex::from((tile_idx.index() as i32 + Self::get_tile_index_shift(&direction)) as u32);
-                board_graph.add_edge(tile_idx, other_idx, direction);
-            }
+                (direction, other_idx)
+            })
+            .collect()
+    }
+
+    pub fn tile_name(tile: TileIndex) -> String {
+        // Algebraic coordinates, tile 0 is a1 and tile 63 is h8
+        let file = (b'a' + (tile.index() % 8) as u8) as char;
+        return format!("{}{}", file, tile.index() / 8 + 1)
+    }
+
+    pub fn tile_coordinate(tile: TileIndex) -> (f32, f32) {
+        // (file, rank) in tile widths, a1 at the origin and White at the bottom
+        return ((tile.index() % 8) as f32, (tile.index() / 8) as f32)
+    }
+
+    pub fn tile_from_name(name: &str) -> Option<TileIndex> {
+        let mut characters = name.chars();
+        let file = characters.next()?;
+        let rank = characters.next()?;
+        if characters.next().is_some() || !('a'..='h').contains(&file) || !('1'..='8').contains(&rank) {
+            return None
         }
-        return TraditionalBoardGraph(board_graph)
+        return Some(TileIndex::new((file as u8 - b'a') as usize + 8 * (rank as u8 - b'1') as usize))
     }
 
     fn new_tile(source_tile: TileIndex) -> Tile<1> {
@@ -85,3 +106,41 @@ impl TraditionalBoardGraph {
         return shift * sign
     }   
 }
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tile_names() {
+        for (tile, name) in [(0, "a1"), (7, "h1"), (56, "a8"), (63, "h8"), (28, "e4"), (35, "d5")] {
+            assert_eq!(
+                TraditionalBoardGraph::tile_name(TileIndex::new(tile)),
+                name
+            );
+            assert_eq!(
+                TraditionalBoardGraph::tile_from_name(name),
+                Some(TileIndex::new(tile))
+            )
+        }
+    }
+
+    #[test]
+    fn test_tile_coordinates() {
+        assert_eq!(TraditionalBoardGraph::tile_coordinate(TileIndex::new(0)), (0.0, 0.0));
+        assert_eq!(TraditionalBoardGraph::tile_coordinate(TileIndex::new(63)), (7.0, 7.0));
+        assert_eq!(TraditionalBoardGraph::tile_coordinate(TileIndex::new(7)), (7.0, 0.0));
+        assert_eq!(TraditionalBoardGraph::tile_coordinate(TileIndex::new(28)), (4.0, 3.0))
+    }
+
+    #[test]
+    fn test_malformed_tile_names() {
+        for name in ["i9", "a0", "e", "e44", "", "E4"] {
+            assert_eq!(
+                TraditionalBoardGraph::tile_from_name(name),
+                None
+            )
+        }
+    }
+}
@@ -17,17 +17,17 @@ pub struct UniformTriangleBoardGraph(pub GraphBoard<1, 6>);
 
 impl UniformTriangleBoardGraph {
     pub fn new() -> Self {
-        let mut board_graph = GraphBoard::new();
-        for tile in 0..55 {
-            board_graph.add_node(Self::new_tile(TileIndex::new(tile)));
-        }
-        for tile_idx in board_graph.node_indices() {
-            for direction in Self::get_valid_directions(tile_idx) {
+        return UniformTriangleBoardGraph(GraphBoard::from_edge_spec(55, Self::new_tile, Self::get_edges))
+    }
+
+    fn get_edges(tile_idx: TileIndex) -> Vec<(TriangularDirection, TileIndex)> {
+        return Self::get_valid_directions(tile_idx)
+            .into_iter()
+            .map(|direction| {
                 let other_idx = TileIndex::from((tile_idx.index() as i32 + Self::get_tile_index_shift(tile_idx, &direction)) as u32);
-                board_graph.add_edge(tile_idx, other_idx, direction);
-            }
-        }
-        return UniformTriangleBoardGraph(board_graph)
+                (direction, other_idx)
+            })
+            .collect()
     }
 
     fn row_length(n: TileIndex) -> i32 {
@@ -98,13 +98,11 @@ impl UniformTriangleBoardGraph {
         }
     }
 
-    pub fn get_y(&self, source_tile: TileIndex) -> f32 {
-        let row_factor = (10 - Self::row_length(source_tile)) as f32;
-        (row_factor.powi(2) / 2.0 - 8.5 * row_factor + (source_tile.index() as f32)) / 2.0
-    }
-
-    pub fn get_x(&self, source_tile: TileIndex) -> f32 {
-        let row_factor = (10 - Self::row_length(source_tile)) as f32;
-        3.0_f32.sqrt() * (row_factor.powi(2) / 2.0 - 10.5 * row_factor + (source_tile.index() as f32)) / 2.0
+    pub fn tile_coordinate(tile: TileIndex) -> (f32, f32) {
+        // Centers of the triangles in tile widths, tile 0 at the origin
+        let row_factor = (10 - Self::row_length(tile)) as f32;
+        let x = 3.0_f32.sqrt() * (row_factor.powi(2) / 2.0 - 10.5 * row_factor + (tile.index() as f32)) / 2.0;
+        let y = (row_factor.powi(2) / 2.0 - 8.5 * row_factor + (tile.index() as f32)) / 2.0;
+        return (x, y)
     }
 }
@@ -0,0 +1,20 @@
+// The chess core: boards, positions, move generation and search, with no dependency on Bevy
+// The GUI in main.rs is just one consumer, build with --no-default-features to leave it out
+pub mod constants;
+pub mod graph_boards;
+pub mod limited_int;
+pub mod position;
+pub mod chess_move;
+pub mod move_generator;
+pub mod piece_set;
+pub mod movement_tables;
+pub mod evaluator;
+pub mod game;
+pub mod bit_board;
+pub mod zobrist;
+pub mod transposition_table;
+pub mod searcher;
+pub mod search_handle;
+pub mod notation;
+pub mod match_runner;
+pub mod uci;
@@ -1,31 +1,65 @@
 use std::collections::HashMap;
 use std::marker::PhantomData;
 
+use serde::{Deserialize, Serialize};
 
-#[derive(Debug, PartialEq, PartialOrd, Eq, Hash, Clone, Copy)]
+
+// Every LimitedInt fits in a u8, so a table this long covers any N
+const VALUE_TABLE_LENGTH: usize = u8::MAX as usize + 1;
+
+#[derive(Debug, PartialEq, PartialOrd, Eq, Hash, Clone, Copy, Serialize, Deserialize)]
 pub struct LimitedInt<const N: u8>(pub u8, PhantomData<u8>);
 
 impl <const N: u8> LimitedInt<N> {
-    pub fn new(value: u8) -> Self {
+    // Built at compile time, only the first N entries are ever read
+    const VALUE_TABLE: [Self; VALUE_TABLE_LENGTH] = {
+        let mut table = [Self(0, PhantomData); VALUE_TABLE_LENGTH];
+        let mut i = 0;
+        while i < VALUE_TABLE_LENGTH {
+            table[i] = Self::new(i as u8);
+            i += 1;
+        }
+        table
+    };
+
+    pub const fn new(value: u8) -> Self {
         return Self(value % N, PhantomData)
     }
 
-    pub fn all_values() -> Vec<Self> {
-        let mut output: Vec<Self> = vec![];
-        for i in 0..N {
-            output.push(Self::new(i));
+    pub fn try_new(value: u8) -> Option<Self> {
+        // Unlike new, values that would wrap are rejected
+        return match value < N {
+            true => Some(Self(value, PhantomData)),
+            false => None
         }
-        return output // return 0..N ????
+    }
+
+    fn from_wide(value: u16) -> Self {
+        // Arithmetic is done in u16 so value + N cannot overflow before the modulo
+        return Self((value % N as u16) as u8, PhantomData)
+    }
+
+    pub fn all_values() -> Vec<Self> {
+        return Self::values().to_vec()
+    }
+
+    pub fn values() -> &'static [Self] {
+        // Same as all_values, without allocating
+        let table: &'static [Self; VALUE_TABLE_LENGTH] = const { &Self::VALUE_TABLE };
+        return &table[..N as usize]
     }
 
     pub fn adjacent_values(&self) -> [LimitedInt<N>; 2] {
-        let value = self.0;
-        let prev = Self::new(value + N - 1);
-        let next = Self::new(value + 1);
+        let value = self.0 as u16;
+        let prev = Self::from_wide(value + N as u16 - 1);
+        let next = Self::from_wide(value + 1);
         [prev, next]
     }
 
     pub fn map_to_other<const T: u8>() -> HashMap<Self, LimitedInt<T>> {
+        // Exact targets are T / N >= 1 apart, so rounding never maps two values to the same target.
+        // With N > T a collision is unavoidable, and no board needs that
+        debug_assert!(N <= T, "map_to_other cannot be injective from {} to {} values", N, T);
         let mut output = HashMap::new();
         for i in 0..N {
             let new_value = (
@@ -41,7 +75,7 @@ impl <const N: u8> LimitedInt<N> {
     }
 
     pub fn shift_by(&self, shift: u8) -> Self {
-        Self::new(self.0 + shift)
+        Self::from_wide(self.0 as u16 + shift as u16)
     }
 }
 
@@ -49,6 +83,7 @@ impl <const N: u8> LimitedInt<N> {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::collections::HashSet;
 
     #[test]
     fn test_new_within_limit() {
@@ -81,6 +116,30 @@ mod tests {
         )
     }
 
+    #[test]
+    fn test_values_matches_all_values() {
+        assert_eq!(
+            LimitedInt::<1>::values(),
+            LimitedInt::<1>::all_values().as_slice()
+        );
+        assert_eq!(
+            LimitedInt::<6>::values(),
+            LimitedInt::<6>::all_values().as_slice()
+        );
+        assert_eq!(
+            LimitedInt::<10>::values(),
+            (0..10).map(LimitedInt::<10>::new).collect::<Vec<_>>().as_slice()
+        );
+        assert_eq!(
+            LimitedInt::<12>::values().len(),
+            12
+        );
+        assert_eq!(
+            LimitedInt::<255>::values().last(),
+            Some(&LimitedInt::<255>::new(254))
+        )
+    }
+
     #[test]
     fn test_map_to_other() {
         let mut result = HashMap::new();
@@ -96,6 +155,25 @@ mod tests {
         )
     }
 
+    #[test]
+    fn test_map_to_other_is_injective() {
+        // Aperiodic tile orientations (6) onto aperiodic (10) and hexagonal (12) style directions
+        let to_ten: HashSet<LimitedInt<10>> = LimitedInt::<6>::map_to_other::<10>().into_values().collect();
+        assert_eq!(
+            to_ten.len(),
+            6
+        );
+        let to_twelve: HashSet<LimitedInt<12>> = LimitedInt::<6>::map_to_other::<12>().into_values().collect();
+        assert_eq!(
+            to_twelve.len(),
+            6
+        );
+        assert_eq!(
+            LimitedInt::<6>::map_to_other::<12>().get(&LimitedInt::<6>::new(1)),
+            Some(&LimitedInt::<12>::new(10))
+        )
+    }
+
     #[test]
     fn test_shift_by() {
         assert_eq!(
@@ -103,5 +181,40 @@ mod tests {
             LimitedInt::<6>::new(1)
         )
     }
-}
 
+    #[test]
+    fn test_shift_by_near_u8_limit() {
+        assert_eq!(
+            LimitedInt::<250>::new(249).shift_by(10),
+            LimitedInt::<250>::new(9)
+        );
+        assert_eq!(
+            LimitedInt::<255>::new(254).shift_by(255),
+            LimitedInt::<255>::new(254)
+        );
+        assert_eq!(
+            LimitedInt::<255>::new(200).adjacent_values(),
+            [LimitedInt::<255>::new(199), LimitedInt::<255>::new(201)]
+        );
+        assert_eq!(
+            LimitedInt::<255>::new(0).adjacent_values(),
+            [LimitedInt::<255>::new(254), LimitedInt::<255>::new(1)]
+        )
+    }
+
+    #[test]
+    fn test_try_new() {
+        assert_eq!(
+            LimitedInt::<6>::try_new(5),
+            Some(LimitedInt::<6>::new(5))
+        );
+        assert_eq!(
+            LimitedInt::<6>::try_new(6),
+            None
+        );
+        assert_eq!(
+            LimitedInt::<6>::try_new(255),
+            None
+        )
+    }
+}
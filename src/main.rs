@@ -1,30 +1,13 @@
-mod constants;
-mod graph_boards;
-mod limited_int;
-mod position;
-mod chess_move;
-mod move_generator;
-mod piece_set;
-mod movement_tables;
-mod evaluator;
-mod game;
-mod bit_board;
-mod zobrist;
-mod transposition_table;
-mod searcher;
-
 use bevy::prelude::*;
 use bevy_egui::{egui, EguiContexts, EguiPlugin};
 use bevy_mod_picking::prelude::*;
 use bevy::sprite::{MaterialMesh2dBundle, Mesh2dHandle};
 
-use graph_boards::traditional_board::TraditionalBoardGraph;
-use graph_boards::hexagonal_board::HexagonalBoardGraph;
-use graph_boards::uniform_triangle_board::UniformTriangleBoardGraph;
-use position::Position;
-use graph_boards::graph_board::TileIndex;
+use aperiodic_chess::graph_boards::hexagonal_board::HexagonalBoardGraph;
+use aperiodic_chess::graph_boards::board_kind::BoardKind;
+use aperiodic_chess::graph_boards::graph_board::TileIndex;
 
-use crate::{game::Game, graph_boards::graph_board::Tile, limited_int::LimitedInt, searcher::Searcher};
+use aperiodic_chess::{game::{Game, MoveKind}, graph_boards::graph_board::Tile, limited_int::LimitedInt, piece_set, search_handle::SearchHandle, searcher::Searcher};
 
 #[derive(Component, Debug, Clone, Copy)]
 pub struct GraphEdge {
@@ -38,6 +21,22 @@ struct MoveIndicator;
 #[derive(Resource)]
 struct CurrentTurnLabel(Entity);
 
+// Side of the square every board is scaled to fit
+const BOARD_SIZE: f32 = 680.0;
+
+// Delay between CPU moves so CPU-vs-CPU games can be watched
+const CPU_MOVE_DELAY_SECONDS: f32 = 1.0;
+
+#[derive(Resource)]
+struct CpuMoveTimer(Timer);
+
+// The CPU gets its own searcher, so Game's engine stays free for move queries while it thinks
+#[derive(Resource, Default)]
+struct CpuSearch {
+    handle: Option<SearchHandle>,
+    idle_searcher: Option<Searcher>
+}
+
 #[derive(Resource, Default)]
 struct GraphState {
     tile_count: u32,
@@ -50,7 +49,21 @@ struct SelectedTile {
     tile_index: Option<TileIndex>,
 }
 
+// A click onto a promotion tile waits here until a piece is picked
+#[derive(Resource, Default)]
+struct PendingPromotion {
+    source_tile: Option<TileIndex>,
+    destination_tile: Option<TileIndex>,
+}
+
 fn main() {
+    // Passing --cpu-vs-cpu lets the engine play both sides
+    let cpu_vs_cpu = std::env::args().any(|arg| arg == "--cpu-vs-cpu");
+    // Passing --board=traditional or --board=hexagonal picks the board, triangular otherwise
+    let board_kind = std::env::args()
+        .find_map(|arg| arg.strip_prefix("--board=").and_then(BoardKind::from_name))
+        .unwrap_or(BoardKind::Triangular);
+
     App::new()
         .add_plugins((
             DefaultPlugins,
@@ -59,16 +72,20 @@ fn main() {
         ))
         .insert_resource(GraphState::default())
         .insert_resource(Game {
-            // engine: Searcher::new(TraditionalBoardGraph::new().0.move_tables()), // TODO: Generalize UI
-            engine: Searcher::new(UniformTriangleBoardGraph::new().0.move_tables()),
-            are_players_cpu: [false, true],
-            // current_position: Position::new_traditional(),
-            current_position: Position::new_triangular(), // TODO: Generalize UI
-            // board: TraditionalBoardGraph::new(),
-            board: UniformTriangleBoardGraph::new(),
-            game_over_state: None
+            engine: Searcher::new(board_kind.shared_move_tables()),
+            are_players_cpu: [cpu_vs_cpu, true],
+            cpu_search_depth: 4,
+            board_kind,
+            current_position: board_kind.initial_position(),
+            game_over_state: None,
+            plies_played: 0,
+            legal_moves: None,
+            legal_move_computations: 0
         })
         .insert_resource(SelectedTile::default())
+        .insert_resource(PendingPromotion::default())
+        .insert_resource(CpuSearch::default())
+        .insert_resource(CpuMoveTimer(Timer::from_seconds(CPU_MOVE_DELAY_SECONDS, TimerMode::Repeating)))
         .add_systems(Startup, setup)
         .add_systems(Update, (
             handle_egui_ui,
@@ -113,8 +130,7 @@ fn setup(
     }).id();
     commands.insert_resource(CurrentTurnLabel(turn_text));
 
-    // spawn_traditional_graph(&mut commands, &mut graph_state, game);
-    spawn_triangular_graph(&mut commands, &mut graph_state, game, meshes, materials);
+    spawn_board_graph(&mut commands, &mut graph_state, game, meshes, materials);
 }
 
 fn despawn_all_graph_entities(
@@ -132,9 +148,29 @@ fn despawn_all_graph_entities(
 
 fn make_cpu_moves(
     mut game: ResMut<Game>,
+    time: Res<Time>,
+    mut cpu_timer: ResMut<CpuMoveTimer>,
+    mut cpu_search: ResMut<CpuSearch>,
 ) {
-    if game.game_over_state == None && game.are_players_cpu[game.current_position.active_player.as_idx()] {
-        game.make_cpu_move()
+    // The search runs on another thread, so this only checks in on it and the window keeps rendering
+    if let Some(handle) = &mut cpu_search.handle {
+        if let Some(search_result) = handle.poll() {
+            let handle = cpu_search.handle.take().unwrap();
+            cpu_search.idle_searcher = Some(handle.into_searcher());
+            game.apply_cpu_result(&search_result);
+        }
+        return
+    }
+    if game.game_over_state != None || !game.are_players_cpu[game.current_position.active_player.as_idx()] {
+        return
+    }
+    // Ticking with the frame delta keeps the pacing independent of frame rate
+    if cpu_timer.0.tick(time.delta()).just_finished() {
+        let searcher = match cpu_search.idle_searcher.take() {
+            Some(searcher) => searcher,
+            None => Searcher::new(game.board_kind.shared_move_tables())
+        };
+        cpu_search.handle = Some(game.start_cpu_search(searcher));
     }
 }
 
@@ -142,17 +178,21 @@ fn handle_tile_click(
     mut event_reader: EventReader<Pointer<Click>>,
     tile_query: Query<&Tile<1>>,
     mut selected_tile: ResMut<SelectedTile>,
+    mut pending_promotion: ResMut<PendingPromotion>,
     mut game: ResMut<Game>,
 ) {
     for event in event_reader.read() {
+        if pending_promotion.destination_tile.is_some() {
+            return // The promotion picker has to be answered first
+        }
         if game.are_players_cpu[game.current_position.active_player.as_idx()] { 
             return // No clicks will register while the AI is thinking
         }
 
         if let Ok(clicked_tile) = tile_query.get(event.target) {
-            // Assume the clicked tile should be selected if it has an occupant
+            // Only select pieces that can actually move
             let original_selected_tile = selected_tile.tile_index;
-            if clicked_tile.occupant != None {
+            if clicked_tile.occupant != None && game.has_moves_from(&clicked_tile.id) {
                 selected_tile.entity = Some(event.target);
                 selected_tile.tile_index = Some(clicked_tile.id);
             }
@@ -160,7 +200,12 @@ fn handle_tile_click(
             if let Some(source_tile) = original_selected_tile {
                 let moves = game.query_tile(&source_tile);
                 if moves.get_bit_at_tile(&clicked_tile.id) {
-                    match game.attempt_move_input(&source_tile, &clicked_tile.id) {
+                    if game.is_promotion_input(&source_tile, &clicked_tile.id) {
+                        pending_promotion.source_tile = Some(source_tile);
+                        pending_promotion.destination_tile = Some(clicked_tile.id);
+                        return
+                    }
+                    match game.attempt_move_input(&source_tile, &clicked_tile.id, None) {
                         Err(_) => {},
                         _ => { // Successful moves reset selected_tile
                             selected_tile.entity = None;
@@ -173,6 +218,60 @@ fn handle_tile_click(
     }
 }
 
+fn spawn_move_marker(parent: &mut ChildBuilder, bundle: PickableBundle, kind: &MoveKind) {
+    // Quiet moves get a small dot, captures a hollow ring, en passant a red dot and promotions a crown
+    let marker_sprite = |color: Color, size: Vec2, offset: Vec3| SpriteBundle {
+        sprite: Sprite {
+            color,
+            custom_size: Some(size),
+            ..default()
+        },
+        transform: Transform::from_translation(offset),
+        ..default()
+    };
+    let dot_color = Color::rgba(0.0, 0.0, 0.0, 0.5);
+    match kind {
+        MoveKind::Quiet => {
+            parent.spawn((MoveIndicator, bundle, marker_sprite(dot_color, Vec2::new(30.0, 30.0), Vec3::new(0.0, 0.0, 0.1))));
+        },
+        MoveKind::Capture => {
+            // Four bars around an invisible anchor, so the captured piece stays readable
+            parent.spawn((MoveIndicator, bundle, marker_sprite(Color::NONE, Vec2::new(80.0, 80.0), Vec3::new(0.0, 0.0, 0.1))))
+                .with_children(|ring| {
+                    for (size, x, y) in [
+                        (Vec2::new(80.0, 8.0), 0.0, 36.0),
+                        (Vec2::new(80.0, 8.0), 0.0, -36.0),
+                        (Vec2::new(8.0, 80.0), 36.0, 0.0),
+                        (Vec2::new(8.0, 80.0), -36.0, 0.0)
+                    ] {
+                        ring.spawn(marker_sprite(dot_color, size, Vec3::new(x, y, 0.0)));
+                    }
+                });
+        },
+        MoveKind::EnPassant => {
+            parent.spawn((MoveIndicator, bundle, marker_sprite(Color::rgba(0.8, 0.1, 0.1, 0.6), Vec2::new(30.0, 30.0), Vec3::new(0.0, 0.0, 0.1))));
+        },
+        MoveKind::Promotion => {
+            // The default font only covers ASCII, so the crown is a gold Q
+            parent.spawn((MoveIndicator, bundle, marker_sprite(dot_color, Vec2::new(30.0, 30.0), Vec3::new(0.0, 0.0, 0.1))))
+                .with_children(|crown| {
+                    crown.spawn(Text2dBundle {
+                        text: Text::from_section(
+                            "Q",
+                            TextStyle {
+                                font_size: 30.0,
+                                color: Color::rgb(0.95, 0.8, 0.1),
+                                ..default()
+                            }
+                        ),
+                        transform: Transform::from_translation(Vec3::new(0.0, 0.0, 0.01)),
+                        ..default()
+                    });
+                });
+        }
+    }
+}
+
 fn spawn_move_indicators(
     mut commands: Commands,
     selected_tile: Res<SelectedTile>,
@@ -185,26 +284,14 @@ fn spawn_move_indicators(
     }
 
     if let Some(tile_index) = selected_tile.tile_index {
-        let moves = game.query_tile(&tile_index);
+        let moves = game.query_tile_detailed(&tile_index);
 
         for (tile, entity) in tile_query.iter() {
-            if moves.get_bit_at_tile(&tile.id) {
+            if let Some((_, kind)) = moves.iter().find(|(destination, _)| destination == &tile.id) {
                 let mut bundle = PickableBundle::default(); // Needed to add this to get the right behavior
                 bundle.pickable.should_block_lower = false;
                 commands.entity(entity).with_children(|parent| {
-                    parent.spawn((
-                        MoveIndicator,
-                        bundle,
-                        SpriteBundle {
-                            sprite: Sprite {
-                                color: Color::rgba(0.0, 0.0, 0.0, 0.5),
-                                custom_size: Some(Vec2::new(30.0, 30.0)),
-                                ..default()
-                            },
-                            transform: Transform::from_translation(Vec3::new(0.0, 0.0, 0.1)),
-                            ..default()
-                        },
-                    ));
+                    spawn_move_marker(parent, bundle, kind);
                 });
             } else if tile.id == tile_index {
                 commands.entity(entity).with_children(|parent| {
@@ -269,7 +356,11 @@ fn update_turn_indicator(
             };
             game.check_if_over();
             if let Some(game_over_condition) = &game.game_over_state {
-                text.sections[0].value = game_over_condition.display(game.current_position.active_player.opponent());
+                text.sections[0].value = game_over_condition.display();
+            } else if game.can_claim_draw().is_some() {
+                text.sections[0].value = format!("{} ({}) to move, a draw can be claimed", player_name, player_type);
+            } else if game.current_position.repetition_count() == 2 {
+                text.sections[0].value = format!("{} ({}) to move, one more repetition allows a draw claim", player_name, player_type);
             } else {
                 text.sections[0].value = format!("{} ({}) to move", player_name, player_type);
             }
@@ -277,51 +368,66 @@ fn update_turn_indicator(
     }
 }
 
-fn spawn_triangular_graph(
+fn spawn_board_graph(
     commands: &mut Commands,
     graph_state: &mut ResMut<GraphState>,
     game: Res<Game>,
     mut meshes: ResMut<Assets<Mesh>>,
     mut materials: ResMut<Assets<ColorMaterial>>,
 ) {
-    const TRIANGLE_RADIUS: f32 = 55.0;
-    let triangle_mesh_handle: Mesh2dHandle = meshes.add(
-        RegularPolygon {
-            sides: 3,
-            circumcircle: Circle::new(TRIANGLE_RADIUS),
-        }
-    ).into();
-
-    // let tile_color = materials.add(Color::rgb(0.92, 0.92, 0.81));
-    let tile_color = materials.add(Color::rgb(0.46, 0.58, 0.33));
+    let layout = game.board_kind.tile_layout(BOARD_SIZE);
+    let spacing = layout.spacing;
+
+    // Each board gets the tile shape that tessellates it, hexagons are turned to put a flat side forward
+    let (tile_mesh_handle, rotation): (Mesh2dHandle, Quat) = match game.board_kind {
+        BoardKind::Traditional => (
+            meshes.add(Rectangle::new(spacing, spacing)).into(),
+            Quat::IDENTITY
+        ),
+        BoardKind::Hexagonal => (
+            meshes.add(RegularPolygon { sides: 6, circumcircle: Circle::new(spacing / 3.0_f32.sqrt()) }).into(),
+            Quat::from_rotation_z(std::f32::consts::PI / 6.0)
+        ),
+        BoardKind::Triangular => (
+            meshes.add(RegularPolygon { sides: 3, circumcircle: Circle::new(spacing * 55.0 / 85.0) }).into(),
+            Quat::from_rotation_z(std::f32::consts::PI)
+        )
+    };
+    // Labels are turned back so they stay upright
+    let text_rotation = rotation.inverse();
 
-    let num_tiles = game.board.0.node_count() as u32;
-    let num_edges = game.board.0.edge_count() as u32;
-    let mut tiles: Vec<(Entity, Tile<1>)> = Vec::with_capacity(num_tiles as usize);
+    let tile_colors = [
+        materials.add(Color::rgb(0.46, 0.58, 0.33)),
+        materials.add(Color::rgb(0.92, 0.92, 0.81)),
+        materials.add(Color::rgb(0.69, 0.75, 0.57))
+    ];
 
-    for i in 0..num_tiles {
-        let x = game.board.get_x(TileIndex::new(i as usize)) * ((600 / 7) as f32);
-        let y = game.board.get_y(TileIndex::new(i as usize)) * ((600 / 7) as f32);
-        let pos = Vec2::new(x - 300.0, y - 300.0);
-        let tile_index = TileIndex::new(i as usize);
+    for (i, (x, y)) in layout.positions.iter().enumerate() {
+        let tile_index = TileIndex::new(i);
         let occupant = game.current_position.get_occupant(&tile_index);
         let mut occupant_char = ' ';
         if let Some(occ) = occupant {
             occupant_char = occ.display();
         }
 
-        let graph_tile_component = Tile { id: TileIndex::new(i as usize), occupant, orientation: LimitedInt::<1>::new(1), pawn_start: None };
+        let graph_tile_component = Tile { id: tile_index, occupant, orientation: LimitedInt::<1>::new(1), pawn_start: None };
 
-        let material_handle = tile_color.clone();
-        let rotation = Quat::from_rotation_z(std::f32::consts::PI);
-        let text_rotation = Quat::from_rotation_z(2.0 * std::f32::consts::PI / 2.0);
+        // Neighboring tiles never share a color, hexagons need three for that
+        let color_idx = match game.board_kind {
+            BoardKind::Traditional => (i + i / 8) % 2,
+            BoardKind::Hexagonal => {
+                let (q, r) = HexagonalBoardGraph::axial_coordinate(tile_index);
+                ((q + r) % 3) as usize
+            },
+            BoardKind::Triangular => 0
+        };
 
-        let tile_entity = commands.spawn((
+        commands.spawn((
             graph_tile_component,
             MaterialMesh2dBundle {
-                mesh: triangle_mesh_handle.clone(),
-                material: material_handle,
-                transform: Transform::from_xyz(pos.x, pos.y, 0.0)
+                mesh: tile_mesh_handle.clone(),
+                material: tile_colors[color_idx].clone(),
+                transform: Transform::from_xyz(*x, *y, 0.0)
                     .with_rotation(rotation),
                 ..default()
             },
@@ -331,7 +437,7 @@ fn spawn_triangular_graph(
                 text: Text::from_section(
                     occupant_char.to_string(),
                     TextStyle {
-                        font_size: 50.0,
+                        font_size: 50.0 * spacing / 85.0,
                         color: Color::BLACK,
                         ..default()
                     }
@@ -340,70 +446,11 @@ fn spawn_triangular_graph(
                     .with_rotation(text_rotation),
                 ..default()
             });
-        })
-        .id();
-        tiles.push((tile_entity, graph_tile_component));
-    }
-
-    graph_state.tile_count = num_tiles;
-    graph_state.edge_count = num_edges;
-}
-
-fn spawn_traditional_graph(commands: &mut Commands, graph_state: &mut ResMut<GraphState>, game: Res<Game>) {
-    let num_tiles = game.board.0.node_count() as u32;
-    let num_edges = game.board.0.edge_count() as u32;
-    let mut tiles: Vec<(Entity, Tile<1>)> = Vec::with_capacity(num_tiles as usize);
-
-    for i in 0..num_tiles {
-        let x = ((i % 8) as f32) * ((600 / 7) as f32);
-        let y = ((i / 8) as f32) * ((600 / 7) as f32);
-        let pos = Vec2::new(x - 300.0, y - 300.0);
-        let tile_index = TileIndex::new(i as usize);
-        let occupant = game.current_position.get_occupant(&tile_index);
-        let mut occupant_char = ' ';
-        if let Some(occ) = occupant {
-            occupant_char = occ.display();
-        }
-
-        let graph_tile_component = Tile { id: TileIndex::new(i as usize), occupant, orientation: LimitedInt::<1>::new(1), pawn_start: None };
-
-        let color = match (i + (i / 8)) % 2 {
-            0 => Color::rgb(0.46, 0.58, 0.33),
-            _ => Color::rgb(0.92, 0.92, 0.81)
-        };
-
-        let tile_entity = commands.spawn((
-            graph_tile_component,
-            SpriteBundle {
-                sprite: Sprite {
-                    color: color,
-                    custom_size: Some(Vec2::new(85.0, 85.0)),
-                    ..default()
-                },
-                transform: Transform::from_xyz(pos.x, pos.y, 0.0),
-                ..default()
-            },
-            PickableBundle::default(),
-        )).with_children(|parent| {
-            parent.spawn(Text2dBundle {
-                text: Text::from_section(
-                    occupant_char.to_string(),
-                    TextStyle {
-                        font_size: 50.0,
-                        color: Color::BLACK,
-                        ..default()
-                    }
-                ),
-                transform: Transform::from_translation(Vec3::new(0.0, 0.0, 0.5)),
-                ..default()
-            });
-        })
-        .id();
-        tiles.push((tile_entity, graph_tile_component));
+        });
     }
 
-    graph_state.tile_count = num_tiles;
-    graph_state.edge_count = num_edges;
+    graph_state.tile_count = layout.positions.len() as u32;
+    graph_state.edge_count = game.board_kind.num_edges() as u32;
 }
 
 fn handle_egui_ui(
@@ -412,7 +459,39 @@ fn handle_egui_ui(
     graph_state: ResMut<GraphState>,
     tile_query: Query<Entity, With<Tile<1>>>,
     edge_query: Query<Entity, With<GraphEdge>>,
+    mut selected_tile: ResMut<SelectedTile>,
+    mut pending_promotion: ResMut<PendingPromotion>,
+    mut game: ResMut<Game>,
 ) {
+    if let (Some(source_tile), Some(destination_tile)) = (pending_promotion.source_tile, pending_promotion.destination_tile) {
+        egui::Window::new("Promote to")
+            .collapsible(false)
+            .resizable(false)
+            .show(contexts.ctx_mut(), |ui| {
+                ui.horizontal(|ui| {
+                    for (label, piece) in [
+                        ("Queen", piece_set::PieceType::Queen),
+                        ("Rook", piece_set::PieceType::Rook),
+                        ("Bishop", piece_set::PieceType::Bishop),
+                        ("Knight", piece_set::PieceType::Knight)
+                    ] {
+                        if ui.button(label).clicked() {
+                            if game.attempt_move_input(&source_tile, &destination_tile, Some(piece)).is_ok() {
+                                selected_tile.entity = None;
+                                selected_tile.tile_index = None;
+                            }
+                            pending_promotion.source_tile = None;
+                            pending_promotion.destination_tile = None;
+                        }
+                    }
+                });
+                if ui.button("Cancel").clicked() {
+                    pending_promotion.source_tile = None;
+                    pending_promotion.destination_tile = None;
+                }
+            });
+    }
+
     egui::Window::new("Graph Controls")
         .default_pos(egui::pos2(10.0, 10.0))
         .show(contexts.ctx_mut(), |ui| {
@@ -423,5 +502,11 @@ fn handle_egui_ui(
             if ui.button("Delete Graph").clicked() {
                 despawn_all_graph_entities(&mut commands, tile_query, edge_query);
             }
+            if game.can_claim_draw().is_some() {
+                ui.separator();
+                if ui.button("Claim Draw").clicked() {
+                    game.claim_draw();
+                }
+            }
         });
 }
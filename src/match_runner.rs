@@ -0,0 +1,101 @@
+use crate::{chess_move::Move, move_generator::MoveTables, piece_set::Color, position::{GameResult, Position}};
+
+
+// Same idea as the Game backstop, kept separate so matches stay headless
+const MAX_MATCH_PLIES: u32 = 400;
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct MatchResult {
+    pub wins_a: u32,
+    pub wins_b: u32,
+    pub draws: u32
+}
+
+// An engine is anything that picks a move for the side to move (None resigns into a draw)
+pub type MatchEngine<'a> = dyn FnMut(&mut Position) -> Option<Move> + 'a;
+
+pub fn run_match(engine_a: &mut MatchEngine, engine_b: &mut MatchEngine, move_tables: &MoveTables, games: usize, start_positions: &[String]) -> MatchResult {
+    // Each start position is played twice in a row so both engines get both colors
+    // There has to be at least one start position
+    assert!(!start_positions.is_empty(), "a match needs at least one start position");
+    let mut result = MatchResult { wins_a: 0, wins_b: 0, draws: 0 };
+    for game in 0..games {
+        let start_position = &start_positions[(game / 2) % start_positions.len()];
+        let a_is_white = game % 2 == 0;
+        let winner = match a_is_white {
+            true => play_game(engine_a, engine_b, move_tables, start_position),
+            false => play_game(engine_b, engine_a, move_tables, start_position)
+        };
+        match (winner, a_is_white) {
+            (None, _) => result.draws += 1,
+            (Some(Color::White), true) | (Some(Color::Black), false) => result.wins_a += 1,
+            _ => result.wins_b += 1
+        }
+    }
+    result
+}
+
+fn play_game(white: &mut MatchEngine, black: &mut MatchEngine, move_tables: &MoveTables, start_position: &str) -> Option<Color> {
+    // Returns the winner, or None for a draw
+    let mut position = Position::from_string(start_position.to_string());
+    let mut plies_played = 0;
+    loop {
+        match position.result(move_tables) {
+            Some(GameResult::WhiteWins) => return Some(Color::White),
+            Some(GameResult::BlackWins) => return Some(Color::Black),
+            Some(GameResult::Draw) => return None,
+            // Engines always claim a draw when they can
+            None => if plies_played >= MAX_MATCH_PLIES || position.draw_claim().is_some() {
+                return None
+            }
+        }
+        let chosen_move = match position.active_player {
+            Color::White => white(&mut position),
+            Color::Black => black(&mut position)
+        };
+        match chosen_move {
+            Some(chess_move) => position.make_legal_move(&chess_move),
+            None => return None
+        }
+        plies_played += 1;
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::graph_boards::board_kind::BoardKind;
+    use crate::searcher::Searcher;
+
+    #[test]
+    fn test_search_beats_middle_move() {
+        let mut searcher = Searcher::new(BoardKind::Triangular.shared_move_tables());
+        let move_tables = BoardKind::Triangular.move_tables();
+        let middle_tables = BoardKind::Triangular.move_tables();
+
+        let mut strong = |position: &mut Position| searcher.get_best_move(position, 2).best_move;
+        let mut middle_move = |position: &mut Position| {
+            // The old placeholder CPU: always plays the middle legal move
+            let legal_moves = middle_tables.get_legal_moves(position);
+            legal_moves.get(legal_moves.len() / 2).cloned()
+        };
+
+        let start_positions = vec!["RKNP6QBP6NP6P17ppppnbnqkr w -".to_string()];
+        let result = run_match(&mut strong, &mut middle_move, &move_tables, 2, &start_positions);
+        assert_eq!(
+            result.wins_a + result.wins_b + result.draws,
+            2
+        );
+        assert!(result.wins_a > result.wins_b)
+    }
+
+    #[test]
+    #[should_panic(expected = "a match needs at least one start position")]
+    fn test_match_without_start_positions() {
+        let move_tables = BoardKind::Triangular.move_tables();
+        let mut resign = |_position: &mut Position| None;
+        let mut also_resign = |_position: &mut Position| None;
+        run_match(&mut resign, &mut also_resign, &move_tables, 2, &[]);
+    }
+}
@@ -1,8 +1,13 @@
 
+use std::{fs, io, path::Path};
+
+use serde::{Deserialize, Serialize};
+
 use crate::{
-    bit_board::{BitBoard, BitBoardMoves}, chess_move::{EnPassantData, Move}, constants::NUM_PIECE_TYPES, graph_boards::graph_board::TileIndex, movement_tables::{JumpTable, PawnTables, SlideTables}, piece_set::{Color, PieceType}, position::Position
+    bit_board::{BitBoard, BitBoardMoves, BitBoardTiles}, chess_move::{AnnotatedMove, EnPassantData, Move}, constants::NUM_PIECE_TYPES, graph_boards::graph_board::TileIndex, movement_tables::{JumpTable, PawnTables, SlideTables}, piece_set::{Color, PieceType}, position::Position
 };
 
+#[derive(Serialize, Deserialize)]
 pub struct MoveTables {
     pub king_table: JumpTable, // king_table is it's own reverse
     pub slide_tables: SlideTables,
@@ -16,6 +21,25 @@ pub struct MoveTables {
 }
 
 impl MoveTables {
+    pub fn to_bytes(&self) -> Vec<u8> {
+        // Every field is plain data, so encoding can't fail
+        return bincode::serialize(self).unwrap()
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Option<MoveTables> {
+        return bincode::deserialize(bytes).ok()
+    }
+
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        // Building the tables for a large board is slow, a saved copy loads in a fraction of the time
+        return fs::write(path, self.to_bytes())
+    }
+
+    pub fn load(path: &Path) -> io::Result<MoveTables> {
+        return MoveTables::from_bytes(&fs::read(path)?)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "not a saved set of move tables"))
+    }
+
     pub fn query_piece(&self, piece_type: &PieceType, source_tile: TileIndex, occupied: BitBoard) -> BitBoard {
         return match piece_type {
             PieceType::King => self.king_table[source_tile],
@@ -27,16 +51,21 @@ impl MoveTables {
         }
     }
 
+    pub fn legal_targets(&self, piece_type: &PieceType, source_tile: TileIndex, occupied: BitBoard, allied: BitBoard) -> BitBoard {
+        // Same as query_piece, but allies cannot be captured so their tiles are never destinations
+        return self.query_piece(piece_type, source_tile, occupied) & !allied
+    }
+
     pub fn query_pawn(&self, color: &Color, source_tile: TileIndex, enemies: &BitBoard, occupied: BitBoard, current_ep_data: &Option<EnPassantData>) -> BitBoard {
         let pawn_tables = match color {
             Color::White => &self.white_pawn_tables,
             Color::Black => &self.black_pawn_tables
         };
         let mut all_moves = BitBoard::empty();
-        let single_moves = pawn_tables.single_table[source_tile] & !occupied;
-        all_moves |= pawn_tables.single_table[source_tile] & !occupied;
+        let single_moves = self.pawn_single_pushes(color, BitBoard::new(1 << source_tile.index()), occupied);
+        all_moves |= single_moves;
         if !single_moves.is_zero() { // Only check double moves if the single_move is unblocked
-            all_moves |= *pawn_tables.double_table[source_tile].get(&BitBoard::empty()).unwrap() & !occupied;
+            all_moves |= pawn_tables.double_table[source_tile].get(&BitBoard::empty()) & !occupied;
         }
         all_moves |= pawn_tables.attack_table[source_tile] & *enemies;
         if let Some(data) = current_ep_data { // Can capture via EP even if no enemy is present
@@ -45,111 +74,593 @@ impl MoveTables {
         all_moves
     }
 
-    fn get_pseudo_moves(&self, position: &Position) -> impl Iterator<Item=Move> {
-        let active_player = &position.active_player;
-        let active_pieces = &position.pieces[active_player.as_idx()];
+    pub fn pseudo_destinations(&self, position: &Position, source_tile: TileIndex) -> BitBoard {
+        // Any piece of the side to move, pawns included, before checking if the king is left in check
+        let active_pieces = position.active_pieces();
+        let enemy_occupied = position.enemy_pieces().occupied;
+        let occupied = active_pieces.occupied | enemy_occupied;
+        return match active_pieces.get_piece_at(&source_tile) {
+            None => BitBoard::empty(),
+            Some(PieceType::Pawn) => self.query_pawn(&position.active_player, source_tile, &enemy_occupied, occupied, &position.record.en_passant_data),
+            Some(piece_type) => self.legal_targets(&piece_type, source_tile, occupied, active_pieces.occupied)
+        }
+    }
+
+    pub fn destinations(&self, position: &mut Position, source_tile: TileIndex) -> BitBoard {
+        let mut destinations = self.pseudo_destinations(position, source_tile);
+        for destination_tile in BitBoardTiles::new(destinations) {
+            // Promotion and double push data don't change whether the king ends up in check
+            if !position.is_legal_move(&Move::new(source_tile, destination_tile, None, None), self) {
+                destinations.flip_bit_at_tile_index(destination_tile);
+            }
+        }
+        destinations
+    }
 
-        let enemy_occupants = position.pieces[position.active_player.opponent().as_idx()].occupied;
-        let all_occupants = enemy_occupants | active_pieces.occupied;
-        let current_ep = &position.record.en_passant_data;
+    pub fn pawn_single_pushes(&self, color: &Color, pawns: BitBoard, occupied: BitBoard) -> BitBoard {
+        // Every unblocked single push for a set of pawns, one shift per offset (just one on the traditional board)
+        let pawn_tables = match color {
+            Color::White => &self.white_pawn_tables,
+            Color::Black => &self.black_pawn_tables
+        };
+        let mut pushes = BitBoard::empty();
+        for (offset, sources) in &pawn_tables.push_shifts {
+            pushes |= pawns.shift_direction(*offset, *sources);
+        }
+        pushes & !occupied
+    }
 
-        let mut piece_iters: Vec<BitBoardMoves> = vec![];
+    pub fn bulk_pawn_moves(&self, position: &Position) -> Vec<Move> {
+        // Every pseudo legal pawn move, found with one shift per offset instead of one query per pawn
+        let pawn_tables = match position.active_player {
+            Color::White => &self.white_pawn_tables,
+            Color::Black => &self.black_pawn_tables
+        };
+        let pawns = position.active_pieces().piece_boards[PieceType::Pawn.as_idx()];
+        let enemies = position.enemy_pieces().occupied;
+        let occupied = position.active_pieces().occupied | enemies;
+        let mut capture_targets = enemies;
+        if let Some(passed_tile) = position.en_passant_capture_tile() {
+            capture_targets |= BitBoard::from_ints(vec![passed_tile.index() as u128]);
+        }
 
-        let mut get_piece_iter = | mut piece_board: BitBoard, piece_type: &PieceType | {
-            let mut is_pawn = false;
-            while !piece_board.is_zero() {
-                let source_tile = piece_board.lowest_one().unwrap();
-
-                let mut next_ep_data = None;
-                let mut promotable_tiles = BitBoard::empty();
-                let mut raw_attacks = if piece_type == &PieceType::Pawn {
-                    is_pawn = true;
-                    let pawn_tables = match active_player {
-                        Color::White => &self.white_pawn_tables,
-                        Color::Black => &self.black_pawn_tables
-                    };
-                    next_ep_data = pawn_tables.en_passant_table[source_tile.index()].clone();
-                    promotable_tiles = pawn_tables.promotion_board;
-                    self.query_pawn(active_player, source_tile, &enemy_occupants, all_occupants, current_ep)
-                } else {
-                    self.query_piece(piece_type, source_tile, all_occupants)
-                };
+        let mut output = vec![];
+        let mut pushers = BitBoard::empty(); // Only pawns with an open single push can double push
+        for (offset, sources) in &pawn_tables.push_shifts {
+            let pushes = pawns.shift_direction(*offset, *sources) & !occupied;
+            pushers |= pushes.shift_direction(-offset, !BitBoard::empty());
+            Self::add_pawn_moves(pawn_tables, pushes, *offset, &mut output);
+        }
+        for (offset, sources) in &pawn_tables.double_shifts {
+            let doubles = pushers.shift_direction(*offset, *sources) & !occupied;
+            Self::add_pawn_moves(pawn_tables, doubles, *offset, &mut output);
+        }
+        for (offset, sources) in &pawn_tables.attack_shifts {
+            let captures = pawns.shift_direction(*offset, *sources) & capture_targets;
+            Self::add_pawn_moves(pawn_tables, captures, *offset, &mut output);
+        }
+        output
+    }
 
-                raw_attacks &= !active_pieces.occupied;
+    fn add_pawn_moves(pawn_tables: &PawnTables, destinations: BitBoard, offset: i32, output: &mut Vec<Move>) {
+        // Each destination came from the tile offset indices behind it
+        for destination_tile in BitBoardTiles::new(destinations) {
+            let source_tile = TileIndex::new((destination_tile.index() as i32 - offset) as usize);
+            let en_passant_tile = match &pawn_tables.en_passant_table[source_tile.index()] {
+                Some(data) if data.occupied_tile == destination_tile => Some(data.passed_tile),
+                _ => None
+            };
+            match pawn_tables.promotion_board.get_bit_at_tile(&destination_tile) {
+                true => for promotion in [PieceType::Knight, PieceType::Bishop, PieceType::Rook, PieceType::Queen] {
+                    output.push(Move::new(source_tile, destination_tile, Some(promotion), en_passant_tile));
+                },
+                false => output.push(Move::new(source_tile, destination_tile, None, en_passant_tile))
+            }
+        }
+    }
 
+    pub fn get_unverified_moves(&self, position: &Position) -> Vec<(Move, bool)> {
+        // Moves paired with whether legality still has to be checked once the move is made,
+        // so a search that makes the move anyway doesn't make it twice
+        let node_attacks = NodeAttacks::new(position, self);
+        let mut output = vec![];
+        for chess_move in self.pseudo_moves(position) {
+            match node_attacks.quick_legality(position, &chess_move, self) {
+                Some(true) => output.push((chess_move, false)),
+                Some(false) => continue,
+                None => output.push((chess_move, true))
+            }
+        }
+        return output
+    }
+
+    fn pseudo_moves(&self, position: &Position) -> impl Iterator<Item=Move> {
+        // Pawns come last, all at once from bulk_pawn_moves
+        let active_pieces = position.active_pieces();
+        let all_occupants = position.enemy_pieces().occupied | active_pieces.occupied;
+
+        let mut piece_iters: Vec<BitBoardMoves> = vec![];
+        for piece_idx in 0..NUM_PIECE_TYPES {
+            let piece_type = PieceType::from_idx(piece_idx);
+            if piece_type == PieceType::Pawn {
+                continue
+            }
+            for source_tile in BitBoardTiles::new(active_pieces.piece_boards[piece_idx]) {
                 piece_iters.push(
                     BitBoardMoves::new(
                         source_tile,
-                        is_pawn,
-                        raw_attacks,
-                        next_ep_data,
-                        promotable_tiles
+                        false,
+                        self.legal_targets(&piece_type, source_tile, all_occupants, active_pieces.occupied),
+                        None,
+                        BitBoard::empty()
                     )
                 );
-                piece_board.flip_bit_at_tile_index(source_tile);
             }
-        };
+        }
+        piece_iters.into_iter().flatten().chain(self.bulk_pawn_moves(position))
+    }
 
-        for piece_idx in 0..NUM_PIECE_TYPES {
-            get_piece_iter(active_pieces.piece_boards[piece_idx], &PieceType::from_idx(piece_idx))
+    pub fn filter_legal(&self, position: &mut Position, pseudo_moves: Vec<Move>, pins: &[(TileIndex, BitBoard)], checkers: BitBoard) -> Vec<Move> {
+        // For callers that already have the pins (Position::pins) and checkers (Position::attackers_of the king)
+        let node_attacks = NodeAttacks::from_check_state(position, self, pins.to_vec(), checkers);
+        let mut legal_moves = vec![];
+        for chess_move in pseudo_moves {
+            if node_attacks.is_legal_move(position, &chess_move, self) {
+                legal_moves.push(chess_move);
+            }
         }
-        piece_iters.into_iter().flatten()
+        legal_moves
     }
 
     pub fn get_legal_moves(&self, position: &mut Position) -> Vec<Move> {
         let mut legal_moves = Vec::new();
-        for chess_move in self.get_pseudo_moves(&position) {
-            if !position.is_legal_move(&chess_move, &self) {
+        self.fill_legal_moves(position, &mut legal_moves);
+        legal_moves
+    }
+
+    pub fn fill_legal_moves(&self, position: &mut Position, legal_moves: &mut Vec<Move>) {
+        // Reuses the caller's buffer, so a search can keep one per ply instead of allocating at every node
+        legal_moves.clear();
+        let node_attacks = NodeAttacks::new(position, self);
+        for chess_move in self.pseudo_moves(&position) {
+            if !node_attacks.is_legal_move(position, &chess_move, self) {
                 continue;
             }
             legal_moves.push(chess_move);
         }
-        legal_moves
+    }
+
+    pub fn get_annotated_moves(&self, position: &mut Position) -> Vec<AnnotatedMove> {
+        // The move is already made to test legality, so checking the opponent's king there is nearly free
+        let mut annotated_moves = Vec::new();
+        let moving_player = position.active_player;
+        for chess_move in self.pseudo_moves(&position) {
+            let is_capture = position.is_capture(&chess_move);
+            position.make_legal_move(&chess_move);
+            let is_legal = !position.is_in_check(self, &moving_player);
+            let gives_check = is_legal && position.is_in_check(self, &position.active_player);
+            position.unmake_legal_move(&chess_move);
+            if is_legal {
+                annotated_moves.push(AnnotatedMove {
+                    is_promotion: chess_move.promotion.is_some(),
+                    chess_move,
+                    is_capture,
+                    gives_check
+                });
+            }
+        }
+        annotated_moves
     }
 
     pub fn has_legal_moves(&self, position: &mut Position) -> bool {
-        for chess_move in self.get_pseudo_moves(&position) {
-            if position.is_legal_move(&chess_move, &self) {
+        let node_attacks = NodeAttacks::new(position, self);
+        for chess_move in self.pseudo_moves(&position) {
+            if node_attacks.is_legal_move(position, &chess_move, self) {
                 return true;
             }
         }
         false
     }
 
+    pub fn has_single_legal_move(&self, position: &mut Position) -> Option<Move> {
+        // Stops as soon as a second legal move is found
+        let node_attacks = NodeAttacks::new(position, self);
+        let mut single_move = None;
+        for chess_move in self.pseudo_moves(&position) {
+            if node_attacks.is_legal_move(position, &chess_move, self) {
+                if single_move.is_some() {
+                    return None
+                }
+                single_move = Some(chess_move);
+            }
+        }
+        single_move
+    }
+
+    pub fn memory_estimate(&self) -> usize {
+        let mut output = self.king_table.memory_estimate()
+            + self.slide_tables.memory_estimate()
+            + self.knight_table.memory_estimate()
+            + self.white_pawn_tables.memory_estimate()
+            + self.black_pawn_tables.memory_estimate()
+            + self.reverse_knight_table.memory_estimate()
+            + self.reverse_white_pawn_table.memory_estimate()
+            + self.reverse_black_pawn_table.memory_estimate();
+        for reverse_table in &self.reverse_slide_tables {
+            output += reverse_table.memory_estimate();
+        }
+        output
+    }
+
     #[allow(unused)]
     pub fn perft(&self, position: &mut Position, depth: u8) -> u64 {
-        let mut output = 0;
-       
-        let legal_moves = self.get_legal_moves(position);
-       
+        let mut move_buffers = vec![Vec::new(); depth as usize];
+        self.perft_with_buffers(position, &mut move_buffers)
+    }
+
+    pub fn perft_iterative(&self, position: &mut Position, depth: u8) -> u64 {
+        // Same count as perft, but walks the tree with an explicit stack of move lists instead of recursing
+        if depth == 0 {
+            return 1
+        }
+        let depth = depth as usize;
+        let mut move_buffers = vec![Vec::new(); depth];
+        let mut next_move = vec![0; depth];
+        self.fill_legal_moves(position, &mut move_buffers[0]);
         if depth == 1 {
-            return legal_moves.len() as u64;
+            return move_buffers[0].len() as u64
+        }
+        let mut output = 0;
+        let mut ply = 0;
+        loop {
+            if next_move[ply] < move_buffers[ply].len() {
+                let legal_move = move_buffers[ply][next_move[ply]].clone();
+                next_move[ply] += 1;
+                position.make_legal_move(&legal_move);
+                if ply + 2 == depth {
+                    // Leaf parents count their moves without making them
+                    self.fill_legal_moves(position, &mut move_buffers[ply + 1]);
+                    output += move_buffers[ply + 1].len() as u64;
+                    position.unmake_legal_move(&legal_move);
+                } else {
+                    ply += 1;
+                    self.fill_legal_moves(position, &mut move_buffers[ply]);
+                    next_move[ply] = 0;
+                }
+            } else {
+                if ply == 0 {
+                    break
+                }
+                ply -= 1;
+                let legal_move = move_buffers[ply][next_move[ply] - 1].clone();
+                position.unmake_legal_move(&legal_move);
+            }
+        }
+        output
+    }
+
+    pub fn perft_divide(&self, position: &mut Position, depth: u8) -> Vec<(Move, u64)> {
+        // The perft count below each root move, for finding which subtree is wrong
+        let mut output = vec![];
+        for legal_move in self.get_legal_moves(position) {
+            position.make_legal_move(&legal_move);
+            let count = match depth {
+                0 | 1 => 1,
+                _ => self.perft(position, depth - 1)
+            };
+            position.unmake_legal_move(&legal_move);
+            output.push((legal_move, count));
+        }
+        output
+    }
+
+    pub fn walk_positions(&self, position: &mut Position, depth: u8, visitor: &mut impl FnMut(&Position)) {
+        // Debug helper, visits every position exactly depth plies away (once per move order reaching it)
+        if depth == 0 {
+            visitor(position);
+            return
         }
-        for legal_move in legal_moves {
+        for legal_move in self.get_legal_moves(position) {
             position.make_legal_move(&legal_move);
-            output += self.perft(position, depth - 1);
+            self.walk_positions(position, depth - 1, visitor);
             position.unmake_legal_move(&legal_move);
         }
+    }
+
+    fn perft_with_buffers(&self, position: &mut Position, move_buffers: &mut [Vec<Move>]) -> u64 {
+        // One buffer per remaining ply, the first is this node's and the rest are handed down
+        let (legal_moves, deeper_buffers) = match move_buffers.split_first_mut() {
+            Some(split) => split,
+            None => return 1
+        };
+        self.fill_legal_moves(position, legal_moves);
+        if deeper_buffers.is_empty() {
+            return legal_moves.len() as u64;
+        }
+        let mut output = 0;
+        for legal_move in legal_moves.iter() {
+            position.make_legal_move(legal_move);
+            output += self.perft_with_buffers(position, deeper_buffers);
+            position.unmake_legal_move(legal_move);
+        }
         output
     }
 }
 
+// Computed once per node so pseudo moves can be judged without a make/unmake, except en passant while in check
+struct NodeAttacks {
+    king_tile: TileIndex,
+    enemy_attacks: BitBoard,
+    in_check: bool,
+    checkers: BitBoard,
+    evasions: BitBoard, // With a single checker, the tiles that capture it or block its line
+    pins: Vec<(TileIndex, BitBoard)>,
+    ep_passed_tile: Option<TileIndex>,
+    ep_captured_tile: Option<TileIndex>
+}
+
+impl NodeAttacks {
+    fn new(position: &Position, move_tables: &MoveTables) -> Self {
+        let active_player = &position.active_player;
+        let king_tile = position.active_pieces().piece_boards[PieceType::King.as_idx()].lowest_one().unwrap();
+        let checkers = position.attackers_of(&king_tile, &active_player.opponent(), move_tables);
+        return Self::from_check_state(position, move_tables, position.pins(active_player, move_tables), checkers)
+    }
+
+    fn from_check_state(position: &Position, move_tables: &MoveTables, pins: Vec<(TileIndex, BitBoard)>, checkers: BitBoard) -> Self {
+        let active_player = &position.active_player;
+        let king_tile = position.active_pieces().piece_boards[PieceType::King.as_idx()].lowest_one().unwrap();
+        NodeAttacks {
+            king_tile,
+            enemy_attacks: position.attack_map(&active_player.opponent(), move_tables),
+            in_check: !checkers.is_zero(),
+            checkers,
+            evasions: Self::evasions(position, move_tables, king_tile, checkers),
+            pins,
+            ep_passed_tile: position.record.en_passant_data.as_ref().map(|data| data.passed_tile),
+            ep_captured_tile: position.record.en_passant_data.as_ref().map(|data| data.occupied_tile)
+        }
+    }
+
+    fn evasions(position: &Position, move_tables: &MoveTables, king_tile: TileIndex, checkers: BitBoard) -> BitBoard {
+        let checker_tile = match checkers.lowest_one() {
+            Some(tile) => tile,
+            None => return BitBoard::empty()
+        };
+        let enemy_pieces = position.enemy_pieces();
+        let occupied = position.pieces[0].occupied | position.pieces[1].occupied;
+        for direction in 0..move_tables.slide_tables.0.len() {
+            let sliders = match direction % 2 {
+                0 => enemy_pieces.orthogonal_sliders(),
+                _ => enemy_pieces.diagonal_sliders()
+            };
+            let line = move_tables.slide_tables[direction][king_tile].get(&occupied);
+            if line.get_bit_at_tile(&checker_tile) && sliders.get_bit_at_tile(&checker_tile) {
+                return line
+            }
+        }
+        checkers // Jumps and pawn attacks can only be captured
+    }
+
+    fn en_passant_exposes_king(&self, position: &Position, chess_move: &Move, move_tables: &MoveTables) -> bool {
+        // Both pawns leave the board at once (often from the same rank), so the usual pin candidates can miss this
+        let mut occupied = position.pieces[0].occupied | position.pieces[1].occupied;
+        occupied.flip_bit_at_tile_index(chess_move.source_tile);
+        occupied.flip_bit_at_tile_index(self.ep_captured_tile.unwrap());
+        occupied.flip_bit_at_tile_index(chess_move.destination_tile);
+
+        let enemy_pieces = position.enemy_pieces();
+        let orthogonal_attackers = enemy_pieces.orthogonal_sliders();
+        let diagonal_attackers = enemy_pieces.diagonal_sliders();
+        let orthogonal_rays = move_tables.slide_tables.query(&self.king_tile, &occupied, true, false);
+        let diagonal_rays = move_tables.slide_tables.query(&self.king_tile, &occupied, false, true);
+        return !(orthogonal_rays & orthogonal_attackers).is_zero() || !(diagonal_rays & diagonal_attackers).is_zero()
+    }
+
+    fn quick_legality(&self, position: &Position, chess_move: &Move, move_tables: &MoveTables) -> Option<bool> {
+        // None when only making the move can tell
+        if chess_move.source_tile == self.king_tile {
+            return Some(!self.enemy_attacks.get_bit_at_tile(&chess_move.destination_tile))
+        }
+        // En passant removes a second piece from the board, so it can expose the king along an unexpected line
+        let is_en_passant = Some(chess_move.destination_tile) == self.ep_passed_tile
+            && position.active_pieces().piece_boards[PieceType::Pawn.as_idx()].get_bit_at_tile(&chess_move.source_tile);
+        if is_en_passant {
+            return match self.in_check {
+                true => None, // The captured pawn may be the checker, which isn't on the destination tile
+                false => Some(!self.en_passant_exposes_king(position, chess_move, move_tables))
+            }
+        }
+        if self.in_check && (self.checkers.0.count_ones() > 1 || !self.evasions.get_bit_at_tile(&chess_move.destination_tile)) {
+            return Some(false) // Only the king can step out of a double check
+        }
+        for (pinned_tile, pin_ray) in &self.pins {
+            if *pinned_tile == chess_move.source_tile {
+                return Some(pin_ray.get_bit_at_tile(&chess_move.destination_tile))
+            }
+        }
+        Some(true)
+    }
+
+    fn is_legal_move(&self, position: &mut Position, chess_move: &Move, move_tables: &MoveTables) -> bool {
+        return match self.quick_legality(position, chess_move, move_tables) {
+            Some(is_legal) => is_legal,
+            None => position.is_legal_move(chess_move, move_tables)
+        }
+    }
+}
+
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::collections::HashMap;
+    use crate::graph_boards::board_kind::BoardKind;
     use crate::graph_boards::traditional_board::TraditionalBoardGraph;
+    use crate::graph_boards::uniform_triangle_board::UniformTriangleBoardGraph;
 
     fn test_move_tables() -> MoveTables {
         let board = TraditionalBoardGraph::new();
         board.0.move_tables()
     }
 
+    // Baseline lines are "source-destination count", promotions add "=Piece" to the move
+    fn perft_move_key(chess_move: &Move) -> String {
+        match &chess_move.promotion {
+            Some(piece) => format!("{}-{}={:?}", chess_move.source_tile.index(), chess_move.destination_tile.index(), piece),
+            None => format!("{}-{}", chess_move.source_tile.index(), chess_move.destination_tile.index())
+        }
+    }
+
+    fn reference_legal_moves(move_tables: &MoveTables, position: &mut Position) -> Vec<Move> {
+        // Make/unmake on every pseudo move, none of the legality shortcuts
+        move_tables.get_annotated_moves(position).into_iter().map(|annotated| annotated.chess_move).collect()
+    }
+
+    fn find_divergence(move_tables: &MoveTables, position: &mut Position, depth: u8, path: &mut Vec<String>) -> Option<String> {
+        // Walks the tree comparing the fast generator to the reference one, returning the first node where they disagree
+        let mut fast: Vec<String> = move_tables.get_legal_moves(position).iter().map(perft_move_key).collect();
+        let mut reference: Vec<String> = reference_legal_moves(move_tables, position).iter().map(perft_move_key).collect();
+        fast.sort();
+        reference.sort();
+        if fast != reference {
+            let extra: Vec<&String> = fast.iter().filter(|key| !reference.contains(key)).collect();
+            let missing: Vec<&String> = reference.iter().filter(|key| !fast.contains(key)).collect();
+            return Some(format!("after [{}]: generated {:?} that should not be, missing {:?}", path.join(" "), extra, missing))
+        }
+        if depth <= 1 {
+            return None
+        }
+        for legal_move in move_tables.get_legal_moves(position) {
+            path.push(perft_move_key(&legal_move));
+            position.make_legal_move(&legal_move);
+            let divergence = find_divergence(move_tables, position, depth - 1, path);
+            position.unmake_legal_move(&legal_move);
+            if divergence.is_some() {
+                return divergence
+            }
+            path.pop();
+        }
+        None
+    }
+
+    fn perft_diff(move_tables: &MoveTables, position: &mut Position, depth: u8, baseline: &str) -> Result<(), String> {
+        let expected: HashMap<&str, u64> = baseline.lines()
+            .filter(|line| !line.trim().is_empty())
+            .map(|line| {
+                let (key, count) = line.split_once(' ').unwrap();
+                (key, count.trim().parse().unwrap())
+            })
+            .collect();
+        let divide = move_tables.perft_divide(position, depth);
+        let mut errors = vec![];
+        for (legal_move, count) in &divide {
+            let key = perft_move_key(legal_move);
+            match expected.get(key.as_str()) {
+                Some(expected_count) if expected_count == count => continue,
+                Some(expected_count) => {
+                    errors.push(format!("{}: counted {}, baseline has {}", key, count, expected_count));
+                    position.make_legal_move(legal_move);
+                    let divergence = find_divergence(move_tables, position, depth - 1, &mut vec![key.clone()]);
+                    position.unmake_legal_move(legal_move);
+                    errors.push(match divergence {
+                        Some(divergence) => format!("  generators diverge {}", divergence),
+                        None => format!("  fast and reference generators agree below {}, check the baseline", key)
+                    });
+                },
+                None => errors.push(format!("{}: generated but not in the baseline", key))
+            }
+        }
+        for key in expected.keys() {
+            if !divide.iter().any(|(legal_move, _count)| perft_move_key(legal_move) == *key) {
+                errors.push(format!("{}: in the baseline but not generated", key));
+            }
+        }
+        match errors.is_empty() {
+            true => Ok(()),
+            false => Err(errors.join("\n"))
+        }
+    }
+
+    #[test]
+    fn test_walk_positions_keeps_incremental_state() {
+        let move_tables = test_move_tables();
+        // The second position reaches en passant (d7-d5, exd6) and promotion on b8 within three plies
+        for mut position in [Position::new_traditional(), Position::from_string("4K31P12P1p11k b -".to_string())] {
+            let mut visited = 0;
+            move_tables.walk_positions(&mut position, 3, &mut |reached: &Position| {
+                visited += 1;
+                assert_eq!(
+                    reached.record.zobrist,
+                    reached.get_zobrist()
+                );
+                for piece_set in &reached.pieces {
+                    assert_eq!(
+                        piece_set.occupied,
+                        piece_set.recompute_occupied()
+                    )
+                }
+            });
+            assert_eq!(
+                visited,
+                move_tables.perft(&mut position, 3)
+            )
+        }
+    }
+
+    fn unverified_perft(move_tables: &MoveTables, position: &mut Position, depth: u8) -> u64 {
+        // Counts the way the search walks moves: make first, then drop the ones that leave the king attacked
+        if depth == 0 {
+            return 1
+        }
+        let moving_player = position.active_player;
+        let mut output = 0;
+        for (chess_move, needs_verifying) in move_tables.get_unverified_moves(position) {
+            position.make_legal_move(&chess_move);
+            if !(needs_verifying && position.is_in_check(move_tables, &moving_player)) {
+                output += unverified_perft(move_tables, position, depth - 1);
+            }
+            position.unmake_legal_move(&chess_move);
+        }
+        output
+    }
+
+    #[test]
+    fn test_unverified_moves_match_perft() {
+        let move_tables = test_move_tables();
+        for fen in ["RNBQKBNRPPPPPPPP32pppppppprnbqkbnr w -", "r15N1b6P6KPp4r23k w 50,42,34"] {
+            assert_eq!(
+                unverified_perft(&move_tables, &mut Position::from_string(fen.to_string()), 3),
+                move_tables.perft(&mut Position::from_string(fen.to_string()), 3)
+            )
+        }
+    }
+
+    #[test]
+    fn test_perft_divide_matches_baseline() {
+        let move_tables = test_move_tables();
+        let mut position = Position::new_traditional();
+        let baseline = include_str!("../perft_baselines/traditional_depth_4.txt");
+        if let Err(report) = perft_diff(&move_tables, &mut position, 4, baseline) {
+            panic!("perft(4) diverged from the baseline:\n{}", report)
+        }
+    }
+
+    #[test]
+    fn test_perft_diff_names_the_diverging_move() {
+        let move_tables = test_move_tables();
+        let mut position = Position::new_traditional();
+        let baseline = include_str!("../perft_baselines/traditional_depth_4.txt").replace("1-16 8885", "1-16 8884");
+        let report = perft_diff(&move_tables, &mut position, 4, &baseline).unwrap_err();
+        assert!(report.starts_with("1-16: counted 8885, baseline has 8884"));
+        assert!(report.contains("agree below 1-16"))
+    }
+
     #[test]
     fn test_perft_one() {
         let move_tables = test_move_tables();
         let mut count = 0;
-        for _chess_move in move_tables.get_pseudo_moves(&mut Position::new_traditional()) {
+        for _chess_move in move_tables.pseudo_moves(&mut Position::new_traditional()) {
             count += 1;
         }
         assert_eq!(
@@ -158,6 +669,128 @@ mod tests {
         )
     }
 
+    #[test]
+    fn test_legal_targets_excludes_allies() {
+        let move_tables = test_move_tables();
+        let source_tile = TileIndex::new(0);
+        let allied = BitBoard::from_ints(vec![0, 16]);
+        assert_eq!( // Raw query includes the allied pawn on a3
+            move_tables.query_piece(&PieceType::Rook, source_tile, allied),
+            BitBoard::from_ints(vec![1, 2, 3, 4, 5, 6, 7, 8, 16])
+        );
+        assert_eq!(
+            move_tables.legal_targets(&PieceType::Rook, source_tile, allied, allied),
+            BitBoard::from_ints(vec![1, 2, 3, 4, 5, 6, 7, 8])
+        )
+    }
+
+    #[test]
+    fn test_destinations() {
+        let move_tables = test_move_tables();
+        // White pawn on e5 next to the black pawn that just moved d7-d5
+        let mut position = Position::from_string("1N2K30pP12P13k w 51,43,35".to_string());
+        assert_eq!(
+            move_tables.destinations(&mut position, TileIndex::new(36)),
+            BitBoard::from_ints(vec![43, 44]) // e6 and d6 en passant
+        );
+        assert_eq!( // Promotion tiles are included like any other
+            move_tables.destinations(&mut position, TileIndex::new(49)),
+            BitBoard::from_ints(vec![57])
+        );
+        assert_eq!(
+            move_tables.destinations(&mut position, TileIndex::new(1)),
+            BitBoard::from_ints(vec![11, 16, 18])
+        );
+        assert_eq!( // Empty tiles and the opponent's pieces have none
+            move_tables.destinations(&mut position, TileIndex::new(35)),
+            BitBoard::empty()
+        );
+        assert_eq!(
+            move_tables.destinations(&mut position, TileIndex::new(2)),
+            BitBoard::empty()
+        )
+    }
+
+    #[test]
+    fn test_bulk_pawn_moves_match_per_tile() {
+        let mut positions = vec![
+            // En passant on d6 and a pawn about to promote on b7
+            Position::from_string("4K3PPP24pP12P13k w 51,43,35".to_string())
+        ];
+        let bulk = test_move_tables().bulk_pawn_moves(&positions[0]);
+        assert!(bulk.contains(&Move::new(TileIndex::new(36), TileIndex::new(43), None, None)));
+        assert_eq!(bulk.iter().filter(|chess_move| chess_move.promotion.is_some()).count(), 4);
+        for board_kind in [BoardKind::Traditional, BoardKind::Hexagonal, BoardKind::Triangular] {
+            let move_tables = board_kind.move_tables();
+            for seed in 0..5 {
+                positions.push(Position::random(board_kind, seed, 30, &move_tables));
+            }
+        }
+        for position in positions {
+            let move_tables = position.board_kind.unwrap().move_tables();
+            let pawns = position.active_pieces().piece_boards[PieceType::Pawn.as_idx()];
+            let enemies = position.enemy_pieces().occupied;
+            let occupied = position.active_pieces().occupied | enemies;
+            let pawn_tables = match position.active_player {
+                Color::White => &move_tables.white_pawn_tables,
+                Color::Black => &move_tables.black_pawn_tables
+            };
+            let mut per_tile: Vec<String> = BitBoardTiles::new(pawns)
+                .flat_map(|source_tile| BitBoardMoves::new(
+                    source_tile,
+                    true,
+                    move_tables.query_pawn(&position.active_player, source_tile, &enemies, occupied, &position.record.en_passant_data),
+                    pawn_tables.en_passant_table[source_tile.index()].clone(),
+                    pawn_tables.promotion_board
+                ))
+                .map(|chess_move| format!("{:?}", chess_move))
+                .collect();
+            let mut bulk: Vec<String> = move_tables.bulk_pawn_moves(&position)
+                .iter()
+                .map(|chess_move| format!("{:?}", chess_move))
+                .collect();
+            per_tile.sort();
+            bulk.sort();
+            assert_eq!(bulk, per_tile);
+        }
+    }
+
+    #[test]
+    fn test_pawn_single_pushes_match_tables() {
+        let move_tables = test_move_tables();
+        // The traditional board pushes by a single shift, the triangular one needs several
+        assert_eq!(move_tables.white_pawn_tables.push_shifts.len(), 1);
+        assert_eq!(move_tables.black_pawn_tables.push_shifts[0].0, -8);
+        let triangular_tables = UniformTriangleBoardGraph::new().0.move_tables();
+        assert!(triangular_tables.white_pawn_tables.push_shifts.len() > 1);
+
+        let position = Position::new_traditional();
+        let occupied = position.pieces[0].occupied | position.pieces[1].occupied;
+        for (color, tables) in [(Color::White, &move_tables), (Color::Black, &move_tables), (Color::White, &triangular_tables)] {
+            let pawn_tables = match color {
+                Color::White => &tables.white_pawn_tables,
+                Color::Black => &tables.black_pawn_tables
+            };
+            // Every tile holding a pawn at once, including the last rank where there is no push
+            let all_tiles = BitBoard::new(u128::MAX >> (128 - tables.king_table.num_tiles()));
+            for blockers in [BitBoard::empty(), occupied] {
+                let mut per_tile = BitBoard::empty();
+                for source_tile in BitBoardTiles::new(all_tiles) {
+                    let expected = pawn_tables.single_table[source_tile] & !blockers;
+                    assert_eq!(
+                        tables.pawn_single_pushes(&color, BitBoard::new(1 << source_tile.index()), blockers),
+                        expected
+                    );
+                    per_tile |= expected;
+                }
+                assert_eq!(
+                    tables.pawn_single_pushes(&color, all_tiles, blockers),
+                    per_tile
+                )
+            }
+        }
+    }
+
     #[test]
     fn test_query_pawn_white() {
         let move_tables = test_move_tables();
@@ -284,6 +917,137 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_pinned_piece_cannot_move() {
+        let move_tables = test_move_tables();
+        // White knight on e2 is pinned to the king on e1 by the rook on e8
+        let mut position = Position::from_string("4K7N47r2k w -".to_string());
+        let legal_moves = move_tables.get_legal_moves(&mut position);
+        assert!(legal_moves.iter().all(|chess_move| chess_move.source_tile == TileIndex::new(4)));
+        assert_eq!(
+            legal_moves.len(),
+            4 // d1, f1, d2 and f2, e2 is blocked by the knight
+        )
+    }
+
+    #[test]
+    fn test_en_passant_cannot_expose_king_along_rank() {
+        let move_tables = test_move_tables();
+        // White king a5, pawn b5 and the black pawn that just reached c5 all share the rook's rank
+        let mut position = Position::from_string("32KPp4r23k w 50,42,34".to_string());
+        let en_passant = Move::new(TileIndex::new(33), TileIndex::new(42), None, None);
+        let node_attacks = NodeAttacks::new(&position, &move_tables);
+        assert!(node_attacks.en_passant_exposes_king(&position, &en_passant, &move_tables));
+        assert!(!node_attacks.is_legal_move(&mut position, &en_passant, &move_tables));
+        assert!(!position.is_legal_move(&en_passant, &move_tables));
+        assert!(!move_tables.get_legal_moves(&mut position).contains(&en_passant));
+
+        // A bishop on the rank is harmless
+        let mut position = Position::from_string("32KPp4b23k w 50,42,34".to_string());
+        let node_attacks = NodeAttacks::new(&position, &move_tables);
+        assert!(!node_attacks.en_passant_exposes_king(&position, &en_passant, &move_tables));
+        assert!(move_tables.get_legal_moves(&mut position).contains(&en_passant))
+    }
+
+    fn sorted_tile_pairs(moves: Vec<Move>) -> Vec<(usize, usize)> {
+        let mut pairs: Vec<(usize, usize)> = moves.iter()
+            .map(|chess_move| (chess_move.source_tile.index(), chess_move.destination_tile.index()))
+            .collect();
+        pairs.sort();
+        pairs
+    }
+
+    #[test]
+    fn test_pin_heavy_legal_moves() {
+        let move_tables = test_move_tables();
+        // White king on a5, knight on a3 pinned by the rook on a1, pawn on b4 pinned by the bishop on c3
+        // Black just played c7-c5, but bxc6 e.p. would clear the fifth rank for the rook on h5
+        let mut position = Position::from_string("r15N1b6P6KPp4r23k w 50,42,34".to_string());
+        assert_eq!(
+            sorted_tile_pairs(move_tables.get_legal_moves(&mut position)),
+            vec![(32, 24), (32, 40), (32, 41), (33, 41)]
+        );
+        assert_eq!( // The make/unmake path without shortcuts agrees
+            sorted_tile_pairs(move_tables.get_annotated_moves(&mut position).into_iter().map(|annotated| annotated.chess_move).collect()),
+            vec![(32, 24), (32, 40), (32, 41), (33, 41)]
+        );
+        // Without the rook on h5 the en passant capture is legal again
+        let mut position = Position::from_string("r15N1b6P6KPp28k w 50,42,34".to_string());
+        assert_eq!(
+            sorted_tile_pairs(move_tables.get_legal_moves(&mut position)),
+            vec![(32, 24), (32, 40), (32, 41), (33, 41), (33, 42)]
+        )
+    }
+
+    fn reference_perft(move_tables: &MoveTables, position: &mut Position, depth: u8) -> u64 {
+        if depth == 0 {
+            return 1
+        }
+        let mut output = 0;
+        for chess_move in reference_legal_moves(move_tables, position) {
+            position.make_legal_move(&chess_move);
+            output += reference_perft(move_tables, position, depth - 1);
+            position.unmake_legal_move(&chess_move);
+        }
+        output
+    }
+
+    #[test]
+    fn test_filter_legal_with_precomputed_check_state() {
+        let move_tables = test_move_tables();
+        for (fen, num_checkers) in [
+            ("r15N1b6P6KPp4r23k w 50,42,34", 0), // Pins, including one only en passant reveals
+            ("4K7N18b28r2k w -", 1), // Pinned knight while checked by the bishop
+            ("4K16n38r2k w -", 2), // Double check
+            ("27K6pP27k w 50,42,34", 1) // Checked by the pawn that en passant captures
+        ] {
+            let mut position = Position::from_string(fen.to_string());
+            let king_tile = position.pieces[0].piece_boards[PieceType::King.as_idx()].lowest_one().unwrap();
+            let pins = position.pins(&Color::White, &move_tables);
+            let checkers = position.attackers_of(&king_tile, &Color::Black, &move_tables);
+            let pseudo_moves = move_tables.pseudo_moves(&position).collect();
+            assert_eq!(
+                checkers.0.count_ones(),
+                num_checkers,
+                "{}", fen
+            );
+            assert_eq!(
+                sorted_tile_pairs(move_tables.filter_legal(&mut position, pseudo_moves, &pins, checkers)),
+                sorted_tile_pairs(reference_legal_moves(&move_tables, &mut position)),
+                "{}", fen
+            );
+            assert_eq!( // Kept shallow, the reference's is_in_check lets the kings walk next to each other
+                move_tables.perft(&mut position, 2),
+                reference_perft(&move_tables, &mut position, 2),
+                "{}", fen
+            )
+        }
+    }
+
+    #[test]
+    fn test_has_single_legal_move() {
+        let move_tables = test_move_tables();
+        // Black king on h8 is checked by the rook on a8, its own pawn on h7 leaves g7 as the only escape
+        let mut position = Position::from_string("K54pR6k b -".to_string());
+        assert_eq!(
+            move_tables.has_single_legal_move(&mut position),
+            Some(Move::new(TileIndex::new(63), TileIndex::new(54), None, None))
+        );
+        assert_eq!(
+            move_tables.has_single_legal_move(&mut Position::new_traditional()),
+            None
+        )
+    }
+
+    #[test]
+    fn test_memory_estimate() {
+        let move_tables = test_move_tables();
+        let memory = move_tables.memory_estimate();
+        println!("Traditional move tables use roughly {} bytes", memory);
+        assert!(memory > move_tables.slide_tables.memory_estimate());
+        assert!(memory < 10_000_000)
+    }
+
     #[test]
     fn test_initial_perft_to_5() {
         let move_tables = test_move_tables();
@@ -294,4 +1058,95 @@ mod tests {
         assert_eq!(move_tables.perft(&mut position, 4), 197281);
         assert_eq!(move_tables.perft(&mut position, 5), 4865609);
     }
+
+    #[test]
+    fn test_perft_iterative_matches_recursive() {
+        // A start position and a pawn race about to promote, both checked against the recursive count
+        let move_tables = test_move_tables();
+        for fen in [Position::new_traditional().to_string(), "K12p36P12k w -".to_string()] {
+            let mut position = Position::from_string(fen.clone());
+            assert_eq!(
+                move_tables.perft_iterative(&mut position, 3),
+                move_tables.perft(&mut position, 3)
+            );
+            assert_eq!(position.to_string(), fen); // Fully unwound
+        }
+        let hexagonal_tables = BoardKind::Hexagonal.move_tables();
+        let mut position = Position::new_hexagonal();
+        assert_eq!(
+            hexagonal_tables.perft_iterative(&mut position, 2),
+            hexagonal_tables.perft(&mut position, 2)
+        )
+    }
+
+    #[test]
+    fn test_perft_across_board_kinds() {
+        // Only traditional counts are standard references, the others are frozen regression baselines
+        // The aperiodic board has no starting position yet, so it isn't covered here
+        let expected_counts = [
+            (BoardKind::Traditional, 4, 197281),
+            (BoardKind::Hexagonal, 3, 133808),
+            (BoardKind::Triangular, 3, 1058)
+        ];
+        for (board_kind, depth, expected) in expected_counts {
+            let move_tables = board_kind.move_tables();
+            let mut position = Position::from_string(board_kind.starting_fen().to_string());
+            assert_eq!(
+                move_tables.perft(&mut position, depth),
+                expected,
+                "{:?} perft({})", board_kind, depth
+            );
+        }
+    }
+
+    #[test]
+    fn test_triangular_perft_to_3() {
+        let move_tables = UniformTriangleBoardGraph::new().0.move_tables();
+        let mut position = Position::new_triangular();
+        assert_eq!(move_tables.perft(&mut position, 1), 10);
+        assert_eq!(move_tables.perft(&mut position, 2), 96);
+        assert_eq!(move_tables.perft(&mut position, 3), 1058);
+    }
+
+    #[test]
+    fn test_triangular_promotion_boards() {
+        // White pawns move along the rows (direction 0), Black pawns move down toward the first row (direction 3)
+        let move_tables = UniformTriangleBoardGraph::new().0.move_tables();
+        assert_eq!(
+            move_tables.white_pawn_tables.promotion_board,
+            BitBoard::from_ints(vec![9, 18, 26, 33, 39, 44, 48, 51, 53, 54])
+        );
+        assert_eq!(
+            move_tables.black_pawn_tables.promotion_board,
+            BitBoard::from_ints(vec![0, 1, 2, 3, 4, 5, 6, 7, 8, 9])
+        )
+    }
+
+    #[test]
+    fn test_serialization_round_trip() {
+        let move_tables = test_move_tables();
+        let occupied = BitBoard::from_ints(vec![12, 19, 42, 51]);
+        // Named per process so concurrent test runs don't share the file
+        let path = std::env::temp_dir().join(format!("aperiodic_chess_test_move_tables_{}.bin", std::process::id()));
+        move_tables.save(&path).unwrap();
+        let loaded = MoveTables::load(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        for piece_type in [PieceType::King, PieceType::Queen, PieceType::Rook, PieceType::Bishop, PieceType::Knight] {
+            for source_tile in [0, 27, 33, 63] {
+                assert_eq!(
+                    loaded.query_piece(&piece_type, TileIndex::new(source_tile), occupied),
+                    move_tables.query_piece(&piece_type, TileIndex::new(source_tile), occupied)
+                )
+            }
+        }
+        assert_eq!(loaded.perft(&mut Position::new_traditional(), 3), 8902);
+        assert!(MoveTables::from_bytes(&[1, 2, 3]).is_none());
+
+        // A board saved and loaded builds the same tables
+        let board = TraditionalBoardGraph::new();
+        let bytes = bincode::serialize(&board.0).unwrap();
+        let loaded_board: crate::graph_boards::graph_board::GraphBoard<1, 8> = bincode::deserialize(&bytes).unwrap();
+        assert_eq!(loaded_board.node_count(), 64);
+        assert_eq!(loaded_board.move_tables().to_bytes(), move_tables.to_bytes())
+    }
 }
@@ -1,12 +1,17 @@
 use std::ops::{Index, IndexMut};
 use std::collections::{HashMap, HashSet};
+use std::mem::size_of;
+
+use rand::rngs::StdRng;
+use rand::{SeedableRng, Rng};
+use serde::{Deserialize, Serialize};
 
 use crate::bit_board::{BitBoard, BitBoardTiles};
 use crate::chess_move::EnPassantData;
 use crate::graph_boards::graph_board::TileIndex;
 
 
-#[derive(Debug, PartialEq, Clone)]
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
 pub struct JumpTable(pub Vec<BitBoard>);
 // JumpTables are a list of BitBoards (one for each tile) for UNBLOCKABLE movement
 
@@ -23,6 +28,10 @@ impl JumpTable {
         return self.0.len()
     }
 
+    pub fn memory_estimate(&self) -> usize {
+        self.0.capacity() * size_of::<BitBoard>()
+    }
+
     pub fn reverse(&self) -> Self {
         let num_tiles = self.num_tiles();
         let mut output = Self::empty(num_tiles);
@@ -52,12 +61,73 @@ impl IndexMut<TileIndex> for JumpTable {
     }
 }
 
-#[derive(Debug, Clone)]
-pub struct DirectionalSlideTable(pub Vec<HashMap<BitBoard, BitBoard>>);
+const MAGIC_SEED: u64 = 2760389141536437263;
+const MAX_MAGIC_ATTEMPTS: usize = 100_000;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MagicEntry {
+    // Maps every occupancy key of a tile's attack map to its own slot (or a slot with the same attacks)
+    mask: BitBoard,
+    magic: u128,
+    shift: u32,
+    attacks: Vec<BitBoard>
+}
+
+impl MagicEntry {
+    pub fn from_attack_map(attack_map: &HashMap<BitBoard, BitBoard>, rng: &mut StdRng) -> Self {
+        let mut mask = BitBoard::empty();
+        for occupied in attack_map.keys() {
+            mask |= *occupied;
+        }
+        // A table of 2^bits slots, grown by one bit if no magic is found (should not happen on real boards)
+        let mut table_bits = mask.0.count_ones().max(1);
+        loop {
+            let shift = 128 - table_bits;
+            for _ in 0..MAX_MAGIC_ATTEMPTS {
+                // Sparse multipliers make good magics
+                let magic = rng.gen::<u128>() & rng.gen::<u128>() & rng.gen::<u128>();
+                if let Some(attacks) = MagicEntry::try_magic(attack_map, mask, magic, shift) {
+                    return Self { mask, magic, shift, attacks }
+                }
+            }
+            table_bits += 1;
+        }
+    }
+
+    fn try_magic(attack_map: &HashMap<BitBoard, BitBoard>, mask: BitBoard, magic: u128, shift: u32) -> Option<Vec<BitBoard>> {
+        let mut slots: Vec<Option<BitBoard>> = vec![None; 1 << (128 - shift)];
+        for (occupied, attacks) in attack_map {
+            let slot = &mut slots[MagicEntry::slot(occupied, mask, magic, shift)];
+            match slot {
+                None => *slot = Some(*attacks),
+                Some(existing) if existing == attacks => {}, // Constructive collision
+                _ => return None
+            }
+        }
+        Some(slots.into_iter().map(|slot| slot.unwrap_or(BitBoard::empty())).collect())
+    }
+
+    fn slot(occupied: &BitBoard, mask: BitBoard, magic: u128, shift: u32) -> usize {
+        return ((occupied.0 & mask.0).wrapping_mul(magic) >> shift) as usize
+    }
+
+    pub fn get(&self, occupied: &BitBoard) -> BitBoard {
+        // Occupied tiles outside the mask are ignored, so the full board occupancy can be passed in
+        return self.attacks[MagicEntry::slot(occupied, self.mask, self.magic, self.shift)]
+    }
+
+    pub fn memory_estimate(&self) -> usize {
+        size_of::<MagicEntry>() + self.attacks.capacity() * size_of::<BitBoard>()
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DirectionalSlideTable(pub Vec<MagicEntry>);
 
 impl DirectionalSlideTable {
     pub fn new(val: Vec<HashMap<BitBoard, BitBoard>>) -> Self {
-        return Self(val)
+        let mut rng = StdRng::seed_from_u64(MAGIC_SEED);
+        return Self(val.iter().map(|attack_map| MagicEntry::from_attack_map(attack_map, &mut rng)).collect())
     }
 
     pub fn reverse(&self) -> JumpTable {
@@ -67,25 +137,29 @@ impl DirectionalSlideTable {
        
         let mut source_tile = 0;
         for source_tile_moves in &self.0 {
-            let unblocked_moves = source_tile_moves.get(&BitBoard::empty()).unwrap();
-            for destination_tile in BitBoardTiles::new(*unblocked_moves) {
+            let unblocked_moves = source_tile_moves.get(&BitBoard::empty());
+            for destination_tile in BitBoardTiles::new(unblocked_moves) {
                 output[destination_tile].flip_bit_at_tile_index(TileIndex::new(source_tile));
             }
             source_tile += 1;
         }
         output
     }
+
+    pub fn memory_estimate(&self) -> usize {
+        self.0.iter().map(|magic_entry| magic_entry.memory_estimate()).sum()
+    }
 }
 
 impl Index<TileIndex> for DirectionalSlideTable {
-    type Output = HashMap<BitBoard, BitBoard>;
+    type Output = MagicEntry;
    
     fn index(&self, index: TileIndex) -> &Self::Output {
         &self.0[index.index()]
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SlideTables(pub Vec<DirectionalSlideTable>);
 
 impl SlideTables {
@@ -104,10 +178,7 @@ impl SlideTables {
             false => 2
         };
         for direction in (initial_direction..self.0.len()).step_by(direction_step) {
-            let directional_map = &self[direction][*source_tile];
-            let unblocked_attacks = *directional_map.get(&BitBoard::empty()).unwrap();
-            let blocked_attacks = *directional_map.get(&(*occupied & unblocked_attacks)).unwrap(); 
-            result |= blocked_attacks;
+            result |= self[direction][*source_tile].get(occupied);
         }
         result
     }
@@ -119,6 +190,10 @@ impl SlideTables {
         }
         output
     }
+
+    pub fn memory_estimate(&self) -> usize {
+        self.0.iter().map(|directional_table| directional_table.memory_estimate()).sum()
+    }
 }
 
 impl Index<usize> for SlideTables {
@@ -129,33 +204,71 @@ impl Index<usize> for SlideTables {
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PawnTables {
     pub single_table: JumpTable,
     pub double_table: DirectionalSlideTable,
     pub attack_table: JumpTable,
     pub en_passant_table: Vec<Option<EnPassantData>>,
-    pub promotion_board: BitBoard
+    pub promotion_board: BitBoard,
+    // Each table split by index offset, with the tiles that move by it, so a whole set of pawns can be shifted at once
+    pub push_shifts: Vec<(i32, BitBoard)>,
+    pub double_shifts: Vec<(i32, BitBoard)>,
+    pub attack_shifts: Vec<(i32, BitBoard)>
 }
 
 impl PawnTables {
     pub fn new(single_table: JumpTable, double_table: DirectionalSlideTable, attack_table: JumpTable) -> Self {
         let en_passant_table = PawnTables::create_en_passant_table(&single_table, &double_table);
         let promotion_board = PawnTables::create_promotion_board(&single_table);
+        let push_shifts = PawnTables::create_shifts(&single_table);
+        let double_targets = (0..single_table.num_tiles())
+            .map(|tile| double_table[TileIndex::new(tile)].get(&BitBoard::empty()))
+            .collect();
+        let double_shifts = PawnTables::create_shifts(&JumpTable::new(double_targets));
+        let attack_shifts = PawnTables::create_shifts(&attack_table);
         Self {
             single_table,
             double_table,
             attack_table,
             en_passant_table,
-            promotion_board
+            promotion_board,
+            push_shifts,
+            double_shifts,
+            attack_shifts
         }
     }
-   
+
+    fn create_shifts(table: &JumpTable) -> Vec<(i32, BitBoard)> {
+        // Works on any board, irregular ones just end up with more offsets
+        let mut shifts: Vec<(i32, BitBoard)> = vec![];
+        for source_tile in 0..table.num_tiles() {
+            let tile_idx = TileIndex::new(source_tile);
+            for destination_tile in BitBoardTiles::new(table[tile_idx]) {
+                let offset = destination_tile.index() as i32 - source_tile as i32;
+                match shifts.iter_mut().find(|(shift_offset, _)| *shift_offset == offset) {
+                    Some((_, sources)) => sources.flip_bit_at_tile_index(tile_idx),
+                    None => shifts.push((offset, BitBoard::from_ints(vec![source_tile as u128])))
+                }
+            }
+        }
+        shifts
+    }
+
+    pub fn memory_estimate(&self) -> usize {
+        self.single_table.memory_estimate()
+            + self.double_table.memory_estimate()
+            + self.attack_table.memory_estimate()
+            + self.en_passant_table.capacity() * size_of::<Option<EnPassantData>>()
+            + (self.push_shifts.capacity() + self.double_shifts.capacity() + self.attack_shifts.capacity()) * size_of::<(i32, BitBoard)>()
+            + size_of::<BitBoard>()
+    }
+
     fn create_en_passant_table(single_table: &JumpTable, double_table: &DirectionalSlideTable) -> Vec<Option<EnPassantData>> {
         let mut output = vec![];
         for source_tile in 0..single_table.num_tiles() {
             let tile_idx = TileIndex::new(source_tile);
-            let en_passant_data = match double_table[tile_idx].get(&BitBoard::empty()).unwrap().lowest_one() {
+            let en_passant_data = match double_table[tile_idx].get(&BitBoard::empty()).lowest_one() {
                 Some(occupied_tile) => {
                     let passed_tile = single_table[tile_idx].lowest_one().unwrap();
                     Some(EnPassantData { source_tile: tile_idx, passed_tile, occupied_tile })
@@ -211,6 +324,19 @@ mod tests {
         )
     }
 
+    #[test]
+    fn test_slide_tables_memory_estimate() {
+        let slide_tables = traditional_slide_tables();
+        // Every tile and direction stores at least one slot per subset of its ray
+        let mut min_entries = 0;
+        for directional_table in &slide_tables.0 {
+            for magic_entry in &directional_table.0 {
+                min_entries += 1 << magic_entry.mask.0.count_ones();
+            }
+        }
+        assert!(slide_tables.memory_estimate() >= min_entries * size_of::<BitBoard>())
+    }
+
     #[test]
     fn test_diagonal_slide_table() {
         let source_tile = TileIndex::new(63);
@@ -254,7 +380,7 @@ mod tests {
         let board = test_traditional_board();
         let source_tile = TileIndex::new(0);
         assert_eq!(
-            *board.0.slide_table_for_direction(&TraditionalDirection::new(0))[source_tile].get(&BitBoard::new(65536)).unwrap(),
+            board.0.slide_table_for_direction(&TraditionalDirection::new(0))[source_tile].get(&BitBoard::new(65536)),
             BitBoard::from_ints(vec![8, 16])
         )
     }
@@ -274,11 +400,11 @@ mod tests {
         let board = test_traditional_board();
         let source_tile = TileIndex::new(8);
         assert_eq!(
-            *board.0.pawn_double_table(&Color::White)[source_tile].get(&BitBoard::empty()).unwrap(),
+            board.0.pawn_double_table(&Color::White)[source_tile].get(&BitBoard::empty()),
             BitBoard::from_ints(vec![24])
         );
         assert_eq!(
-            *board.0.pawn_double_table(&Color::White)[source_tile].get(&BitBoard::from_ints(vec![16])).unwrap(),
+            board.0.pawn_double_table(&Color::White)[source_tile].get(&BitBoard::from_ints(vec![16])),
             BitBoard::empty()
         );
     }
@@ -288,11 +414,11 @@ mod tests {
         let board = test_traditional_board();
         let source_tile = TileIndex::new(48);
         assert_eq!(
-            *board.0.pawn_double_table(&Color::Black)[source_tile].get(&BitBoard::empty()).unwrap(),
+            board.0.pawn_double_table(&Color::Black)[source_tile].get(&BitBoard::empty()),
             BitBoard::from_ints(vec![32])
         );
         assert_eq!(
-            *board.0.pawn_double_table(&Color::Black)[source_tile].get(&BitBoard::from_ints(vec![40])).unwrap(),
+            board.0.pawn_double_table(&Color::Black)[source_tile].get(&BitBoard::from_ints(vec![40])),
             BitBoard::empty()
         );
     }
@@ -371,7 +497,7 @@ mod tests {
         let board = test_hexagonal_board();
         let source_tile = TileIndex::new(56);
         assert_eq!(
-            *board.0.pawn_double_table(&Color::Black)[source_tile].get(&BitBoard::empty()).unwrap(),
+            board.0.pawn_double_table(&Color::Black)[source_tile].get(&BitBoard::empty()),
             BitBoard::from_ints(vec![34])
         )
     }
@@ -0,0 +1,259 @@
+use crate::chess_move::Move;
+use crate::graph_boards::board_kind::BoardKind;
+use crate::graph_boards::graph_board::TileIndex;
+use crate::graph_boards::traditional_board::TraditionalBoardGraph;
+use crate::move_generator::MoveTables;
+use crate::piece_set::PieceType;
+use crate::position::Position;
+
+
+fn piece_letter(piece_type: &PieceType) -> &'static str {
+    return match piece_type {
+        PieceType::King => "K",
+        PieceType::Queen => "Q",
+        PieceType::Rook => "R",
+        PieceType::Bishop => "B",
+        PieceType::Knight => "N",
+        PieceType::Pawn => ""
+    }
+}
+
+fn file_name(tile: &TileIndex) -> String {
+    ((b'a' + (tile.index() % 8) as u8) as char).to_string()
+}
+
+fn rank_name(tile: &TileIndex) -> String {
+    (tile.index() / 8 + 1).to_string()
+}
+
+fn tile_name(tile: &TileIndex, board_kind: BoardKind) -> String {
+    // Only the traditional board has files and ranks, other boards fall back to the tile index
+    return match board_kind {
+        BoardKind::Traditional => TraditionalBoardGraph::tile_name(*tile),
+        _ => tile.index().to_string()
+    }
+}
+
+fn disambiguation(position: &mut Position, chess_move: &Move, move_tables: &MoveTables, board_kind: BoardKind) -> String {
+    let active_pieces = position.active_pieces();
+    let moving_piece = active_pieces.get_piece_at(&chess_move.source_tile);
+    let mut rivals = vec![];
+    for other_move in move_tables.get_legal_moves(position) {
+        if other_move.destination_tile == chess_move.destination_tile
+            && other_move.source_tile != chess_move.source_tile
+            && position.active_pieces().get_piece_at(&other_move.source_tile) == moving_piece
+            && !rivals.contains(&other_move.source_tile) {
+            rivals.push(other_move.source_tile);
+        }
+    }
+    if rivals.is_empty() {
+        return "".to_string()
+    }
+    if board_kind != BoardKind::Traditional {
+        return chess_move.source_tile.index().to_string() + "-"
+    }
+    let source_file = file_name(&chess_move.source_tile);
+    let source_rank = rank_name(&chess_move.source_tile);
+    if rivals.iter().all(|rival| file_name(rival) != source_file) {
+        source_file
+    } else if rivals.iter().all(|rival| rank_name(rival) != source_rank) {
+        source_rank
+    } else {
+        source_file + &source_rank
+    }
+}
+
+pub fn move_to_san(position: &mut Position, chess_move: &Move, move_tables: &MoveTables, board_kind: BoardKind) -> String {
+    // Assumes the move is legal in the given position
+    let moving_piece = position.active_pieces().get_piece_at(&chess_move.source_tile).unwrap();
+    let is_capture = position.is_capture(chess_move);
+    let mut output = piece_letter(&moving_piece).to_string();
+
+    if moving_piece == PieceType::Pawn {
+        if is_capture { // Capturing pawns are identified by their origin
+            output.push_str(&match board_kind {
+                BoardKind::Traditional => file_name(&chess_move.source_tile),
+                _ => chess_move.source_tile.index().to_string()
+            });
+        }
+    } else {
+        output.push_str(&disambiguation(position, chess_move, move_tables, board_kind));
+    }
+
+    if is_capture {
+        output.push('x');
+    }
+    output.push_str(&tile_name(&chess_move.destination_tile, board_kind));
+
+    if let Some(promotion) = &chess_move.promotion {
+        output.push('=');
+        output.push_str(piece_letter(promotion));
+    }
+
+    position.make_legal_move(chess_move);
+    if position.is_in_check(move_tables, &position.active_player) {
+        match move_tables.has_legal_moves(position) {
+            true => output.push('+'),
+            false => output.push('#')
+        }
+    }
+    position.unmake_legal_move(chess_move);
+    output
+}
+
+pub fn move_to_coordinate(chess_move: &Move) -> String {
+    // Long algebraic form used by UCI on the traditional board, e.g. "e2e4" or "e7e8q"
+    let mut output = tile_name(&chess_move.source_tile, BoardKind::Traditional) + &tile_name(&chess_move.destination_tile, BoardKind::Traditional);
+    if let Some(promotion) = &chess_move.promotion {
+        output.push_str(&piece_letter(promotion).to_lowercase());
+    }
+    output
+}
+
+pub fn coordinate_to_move(position: &mut Position, coordinate: &str, move_tables: &MoveTables) -> Option<Move> {
+    // Matched against the legal moves so en passant data is filled in
+    move_tables.get_legal_moves(position)
+        .into_iter()
+        .find(|legal_move| move_to_coordinate(legal_move) == coordinate)
+}
+
+pub fn position_from_fen(fen: &str) -> Option<Position> {
+    // Standard FEN for the traditional board, castling rights are ignored since there is no castling yet
+    let fields: Vec<&str> = fen.split_whitespace().collect();
+    let ranks: Vec<&str> = fields.first()?.split('/').collect();
+    if ranks.len() != 8 {
+        return None
+    }
+    // Standard FEN lists rank 8 first, this engine lists tiles from a1 upward
+    // Empty runs are re-counted across rank boundaries, "8/8" is 16 empty tiles rather than "88"
+    let mut tiles = String::new();
+    let mut empty_tiles = 0;
+    for rank in ranks.iter().rev() {
+        for character in rank.chars() {
+            match character.to_digit(10) {
+                Some(count) => empty_tiles += count,
+                None => {
+                    if empty_tiles > 0 {
+                        tiles.push_str(&empty_tiles.to_string());
+                        empty_tiles = 0;
+                    }
+                    tiles.push(character);
+                }
+            }
+        }
+    }
+    let active_player = match fields.get(1) {
+        Some(&"b") => "b",
+        _ => "w"
+    };
+    let en_passant = match fields.get(3).and_then(|square| TraditionalBoardGraph::tile_from_name(square)) {
+        Some(passed_tile) => {
+            // The pawn that double pushed came from behind the passed tile and now stands in front of it
+            // so the passed tile has to be on rank 6 with white to move, or rank 3 with black to move
+            let (source_tile, occupied_tile) = match (active_player, passed_tile.index() / 8) {
+                ("b", 2) => (passed_tile.index() - 8, passed_tile.index() + 8),
+                ("w", 5) => (passed_tile.index() + 8, passed_tile.index() - 8),
+                _ => return None
+            };
+            format!("{},{},{}", source_tile, passed_tile.index(), occupied_tile)
+        },
+        None => "-".to_string()
+    };
+    Some(Position::from_string(format!("{} {} {}", tiles, active_player, en_passant)))
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::graph_boards::traditional_board::TraditionalBoardGraph;
+
+    fn test_move_tables() -> MoveTables {
+        TraditionalBoardGraph::new().0.move_tables()
+    }
+
+    #[test]
+    fn test_quiet_promotion_san() {
+        let move_tables = test_move_tables();
+        let mut position = Position::from_string("K39k11P8r w -".to_string());
+        let promotion = Move::new(TileIndex::new(52), TileIndex::new(60), Some(PieceType::Queen), None);
+        assert_eq!(
+            move_to_san(&mut position, &promotion, &move_tables, BoardKind::Traditional),
+            "e8=Q"
+        );
+        assert_eq!(
+            move_to_san(&mut position, &promotion, &move_tables, BoardKind::Hexagonal),
+            "60=Q"
+        )
+    }
+
+    #[test]
+    fn test_capture_promotion_san() {
+        let move_tables = test_move_tables();
+        let mut position = Position::from_string("K39k11P8r w -".to_string());
+        let promotion = Move::new(TileIndex::new(52), TileIndex::new(61), Some(PieceType::Knight), None);
+        assert_eq!(
+            move_to_san(&mut position, &promotion, &move_tables, BoardKind::Traditional),
+            "exf8=N"
+        );
+        assert_eq!(
+            move_to_san(&mut position, &promotion, &move_tables, BoardKind::Hexagonal),
+            "52x61=N"
+        )
+    }
+
+    #[test]
+    fn test_en_passant_san() {
+        let move_tables = test_move_tables();
+        let mut position = Position::from_string("K34pP26k w 51,43,35".to_string());
+        let en_passant = Move::new(TileIndex::new(36), TileIndex::new(43), None, None);
+        assert_eq!(
+            move_to_san(&mut position, &en_passant, &move_tables, BoardKind::Traditional),
+            "exd6"
+        )
+    }
+
+    #[test]
+    fn test_coordinate_moves() {
+        let move_tables = test_move_tables();
+        let mut position = Position::new_traditional();
+        let double_push = coordinate_to_move(&mut position, "e2e4", &move_tables).unwrap();
+        assert_eq!(
+            double_push,
+            Move::new(TileIndex::new(12), TileIndex::new(28), None, Some(TileIndex::new(20)))
+        );
+        assert_eq!(
+            move_to_coordinate(&double_push),
+            "e2e4"
+        );
+        assert_eq!( // Not legal here
+            coordinate_to_move(&mut position, "e2e5", &move_tables),
+            None
+        );
+        assert_eq!(
+            move_to_coordinate(&Move::new(TileIndex::new(52), TileIndex::new(60), Some(PieceType::Queen), None)),
+            "e7e8q"
+        )
+    }
+
+    #[test]
+    fn test_position_from_fen() {
+        assert_eq!(
+            position_from_fen("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1").unwrap().get_zobrist(),
+            Position::new_traditional().get_zobrist()
+        );
+        let position = position_from_fen("rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1").unwrap();
+        assert_eq!(
+            position.en_passant_capture_tile(),
+            Some(TileIndex::new(20))
+        );
+        assert_eq!(
+            position.active_player,
+            crate::piece_set::Color::Black
+        );
+        assert!(position_from_fen("8/8/8 w - -").is_none());
+        // An en passant square off the rank the last double push passed over is rejected
+        assert!(position_from_fen("4k3/8/8/8/8/8/8/4K3 w - a1 0 1").is_none());
+        assert!(position_from_fen("rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR w KQkq e3 0 1").is_none())
+    }
+}
@@ -1,11 +1,13 @@
 use std::fmt;
 
+use serde::{Deserialize, Serialize};
+
 use crate::bit_board::BitBoard;
 use crate::constants::NUM_PIECE_TYPES;
 use crate::graph_boards::graph_board::TileIndex;
 
 
-#[derive(Debug, PartialEq, Clone, Copy)]
+#[derive(Debug, PartialEq, Clone, Copy, Serialize, Deserialize)]
 pub enum Color {
     White,
     Black
@@ -38,7 +40,7 @@ impl fmt::Display for Color {
 }
 
 
-#[derive(Debug, Clone, PartialEq, Copy)]
+#[derive(Debug, Clone, PartialEq, Copy, Serialize, Deserialize)]
 pub enum PieceType {
     King,
     Queen,
@@ -102,7 +104,7 @@ impl PieceType {
 }
 
 
-#[derive(Debug, Clone, PartialEq, Copy)]
+#[derive(Debug, Clone, PartialEq, Copy, Serialize, Deserialize)]
 pub struct Piece {
     pub piece: PieceType,
     pub color: Color
@@ -126,7 +128,7 @@ impl Piece {
 }
 
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct PieceSet {
     // pub king: BitBoard,
     // pub queen: BitBoard,
@@ -146,12 +148,25 @@ impl PieceSet {
         }
     }
 
-    pub fn update_occupied(&mut self) {
+    pub fn recompute_occupied(&self) -> BitBoard {
         let mut occupied = BitBoard::empty();
         for piece_board in self.piece_boards {
             occupied |= piece_board
         }
-        self.occupied = occupied
+        occupied
+    }
+
+    pub fn update_occupied(&mut self) {
+        self.occupied = self.recompute_occupied()
+    }
+
+    pub fn check_invariant(&self) {
+        // Catches a mutation that forgot to keep occupied in step with the piece boards
+        debug_assert_eq!(
+            self.occupied,
+            self.recompute_occupied(),
+            "occupied does not match the piece boards"
+        );
     }
 
     pub fn get_piece_at(&self, tile_index: &TileIndex) -> Option<PieceType> {
@@ -163,6 +178,18 @@ impl PieceSet {
         return None
     }
 
+    pub fn orthogonal_sliders(&self) -> BitBoard {
+        return self.piece_boards[PieceType::Rook.as_idx()] | self.piece_boards[PieceType::Queen.as_idx()]
+    }
+
+    pub fn diagonal_sliders(&self) -> BitBoard {
+        return self.piece_boards[PieceType::Bishop.as_idx()] | self.piece_boards[PieceType::Queen.as_idx()]
+    }
+
+    pub fn sliders(&self) -> BitBoard {
+        return self.orthogonal_sliders() | self.diagonal_sliders()
+    }
+
     pub fn get_bitboard_for_piece(&mut self, piece_type: &PieceType) -> &mut BitBoard {
         return &mut self.piece_boards[piece_type.as_idx()]
     }
@@ -228,6 +255,26 @@ mod tests {
         )
     }
 
+    #[test]
+    fn test_sliders() {
+        let piece_set = &Position::new_traditional().pieces[0];
+        let rooks = piece_set.piece_boards[PieceType::Rook.as_idx()];
+        let bishops = piece_set.piece_boards[PieceType::Bishop.as_idx()];
+        let queen = piece_set.piece_boards[PieceType::Queen.as_idx()];
+        assert_eq!(
+            piece_set.orthogonal_sliders(),
+            rooks | queen
+        );
+        assert_eq!(
+            piece_set.diagonal_sliders(),
+            bishops | queen
+        );
+        assert_eq!(
+            piece_set.sliders(),
+            rooks | bishops | queen
+        )
+    }
+
     #[test]
     fn test_move_piece() {
         let piece_set = &mut Position::new_traditional().pieces[0];
@@ -302,4 +349,14 @@ mod tests {
             BitBoard::new(65534) // 2 ** 16 - 2
         )
     }
-}
\ No newline at end of file
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic(expected = "occupied does not match the piece boards")]
+    fn test_desynced_occupied_panics() {
+        let piece_set = &mut Position::new_traditional().pieces[0];
+        piece_set.check_invariant();
+        piece_set.capture_piece(TileIndex::new(0)); // Without update_occupied
+        piece_set.check_invariant();
+    }
+}
@@ -1,13 +1,17 @@
 use std::sync::Arc;
 use lazy_static::lazy_static;
+use rand::rngs::StdRng;
+use rand::{SeedableRng, Rng};
 
 use crate::bit_board::{BitBoard, BitBoardTiles};
+use crate::graph_boards::board_kind::BoardKind;
 use crate::graph_boards::graph_board::{TileIndex};
-use crate::chess_move::{EnPassantData, Move};
+use crate::chess_move::{AnnotatedMove, EnPassantData, Move};
 use crate::move_generator::MoveTables;
 use crate::piece_set::{Color, Piece, PieceType, PieceSet};
 use crate::zobrist::ZobristTable;
-use crate::constants::{MAX_NUM_TILES};
+use crate::constants::{MAX_NUM_TILES, NUM_PIECE_TYPES};
+use crate::evaluator::PIECE_SCORES;
 
 
 lazy_static! {
@@ -16,21 +20,79 @@ lazy_static! {
 
 // static ZOBRIST_TABLE: ZobristTable = ZobristTable::generate();
 
-#[derive(Debug, PartialEq)]
+// Why a game ended, checkmate carries the winner
+#[derive(Debug, PartialEq, Clone, Copy)]
 pub enum GameOver {
-    Checkmate,
-    Draw
+    Checkmate(Color),
+    Draw(DrawReason),
+    MoveLimit // Game's backstop on very long games
 }
 
 impl GameOver {
-    pub fn display(&self, winning_player: Color) -> String {
+    pub fn display(&self) -> String {
+        match self {
+            GameOver::Checkmate(winning_player) => format!("{} wins!", winning_player),
+            GameOver::Draw(reason) => format!("{} — draw.", reason.name()),
+            GameOver::MoveLimit => format!("Move limit — draw.")
+        }
+    }
+
+    pub fn result(&self) -> GameResult {
         match self {
-            GameOver::Checkmate => format!("{} wins!", winning_player),
-            GameOver::Draw => format!("Draw!")
+            GameOver::Checkmate(Color::White) => GameResult::WhiteWins,
+            GameOver::Checkmate(Color::Black) => GameResult::BlackWins,
+            _ => GameResult::Draw
         }
     }
 }
 
+// Which rule drew the position
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum DrawReason {
+    Stalemate,
+    FiftyMove,
+    Repetition,
+    InsufficientMaterial
+}
+
+impl DrawReason {
+    pub fn name(&self) -> &'static str {
+        match self {
+            DrawReason::Stalemate => "Stalemate",
+            DrawReason::FiftyMove => "Fifty-move rule",
+            DrawReason::Repetition => "Repetition",
+            DrawReason::InsufficientMaterial => "Insufficient material"
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum GameResult {
+    WhiteWins,
+    BlackWins,
+    Draw
+}
+
+// Draws a player may claim, the game only ends on its own at fivefold or seventy-five moves
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum DrawClaim {
+    ThreefoldRepetition,
+    FiftyMoves
+}
+
+// The move counters count plies, so fifty moves by each player is a hundred
+pub const FIFTY_MOVE_PLIES: u32 = 100;
+const SEVENTY_FIVE_MOVE_PLIES: u32 = 150;
+
+#[derive(Debug, PartialEq)]
+pub enum PositionError {
+    WrongKingCount(Color),
+    OpponentInCheck,
+    PawnOnPromotionTile(TileIndex),
+    PieceOffBoard(TileIndex),
+    TileCountMismatch(usize)
+}
+
 #[derive(Debug)]
 pub struct PositionRecord {
     pub en_passant_data: Option<EnPassantData>,
@@ -59,7 +121,7 @@ impl PositionRecord {
             passed_tile: TileIndex::new(tile_indices[1].parse().unwrap()),
             occupied_tile: TileIndex::new(tile_indices[2].parse().unwrap())
         });
-        initial_zobrist ^= ZOBRIST_TABLE.en_passant[source_tile_idx];
+        initial_zobrist ^= ZOBRIST_TABLE.en_passant[en_passant_data.as_ref().unwrap().passed_tile.index()];
         PositionRecord { en_passant_data, captured_piece: None, previous_record: None, zobrist: initial_zobrist, fifty_move_counter: 0 }
     }
    
@@ -69,12 +131,18 @@ impl PositionRecord {
 }
 
 
-#[derive(Debug)]
+// Clones share the history of records, which is never changed in place, so a clone can go to another thread
+#[derive(Debug, Clone)]
 pub struct Position {
     pub active_player: Color,
     pub pieces: [PieceSet; 2],
-    pub record: Arc<PositionRecord>
-    // pub board_type
+    pub record: Arc<PositionRecord>,
+    pub board_kind: Option<BoardKind>, // None when a FEN left off trailing empty tiles, so the board is unknown
+    // Zobrist keys of every earlier position, oldest first, a flat copy of the record chain for quick scans
+    pub zobrist_history: Vec<u64>,
+    // The earlier positions themselves, newest last, so repetition can confirm zobrist matches exactly
+    // Off unless keep_exact_history is called, since it copies the board on every move
+    pub exact_history: Option<Vec<Position>>
     // pub properties
 }
 
@@ -89,9 +157,34 @@ impl Position {
         }
     }
 
+    pub fn side_to_move(&self) -> Color {
+        return self.active_player
+    }
+
+    pub fn active_pieces(&self) -> &PieceSet {
+        return &self.pieces[self.active_player.as_idx()]
+    }
+
+    pub fn enemy_pieces(&self) -> &PieceSet {
+        return &self.pieces[self.active_player.opponent().as_idx()]
+    }
+
+    pub fn active_pieces_mut(&mut self) -> &mut PieceSet {
+        return &mut self.pieces[self.active_player.as_idx()]
+    }
+
+    pub fn enemy_pieces_mut(&mut self) -> &mut PieceSet {
+        return &mut self.pieces[self.active_player.opponent().as_idx()]
+    }
+
+    fn num_tiles(&self) -> usize {
+        // Every tile a piece could be on, all of them when the board is unknown
+        return self.board_kind.map_or(MAX_NUM_TILES, |board_kind| board_kind.num_tiles())
+    }
+
     pub fn get_zobrist(&self) -> u64 {
         let mut output = 0;
-        for tile_index in 0..MAX_NUM_TILES {
+        for tile_index in 0..self.num_tiles() {
             if let Some(occupant) = self.get_occupant(&TileIndex::new(tile_index)) {
                 let piece_idx = occupant.piece.as_idx();
                 output ^= ZOBRIST_TABLE.pieces[occupant.color.as_idx()][piece_idx][tile_index]
@@ -106,6 +199,46 @@ impl Position {
         return output
     }
 
+    pub fn canonical_zobrist(&self) -> u64 {
+        // A position and its left to right mirror image share this key, on boards with that symmetry
+        // Whichever of the two has the smaller piece boards is the one that gets hashed
+        let board_kind = match self.board_kind {
+            Some(board_kind) if board_kind.is_mirror_symmetric() => board_kind,
+            _ => return self.record.zobrist
+        };
+        let mirror = |tile: TileIndex| board_kind.mirror_tile(tile).unwrap();
+        let mut mirrored_boards = [[0u128; NUM_PIECE_TYPES]; 2];
+        let mut piece_boards = [[0u128; NUM_PIECE_TYPES]; 2];
+        for player_idx in 0..2 {
+            for piece_idx in 0..NUM_PIECE_TYPES {
+                let piece_board = self.pieces[player_idx].piece_boards[piece_idx];
+                piece_boards[player_idx][piece_idx] = piece_board.0;
+                for tile in BitBoardTiles::new(piece_board) {
+                    mirrored_boards[player_idx][piece_idx] |= 1 << mirror(tile).index();
+                }
+            }
+        }
+        if piece_boards <= mirrored_boards {
+            return self.record.zobrist
+        }
+
+        let mut output = 0;
+        for player_idx in 0..2 {
+            for piece_idx in 0..NUM_PIECE_TYPES {
+                for tile in BitBoardTiles::new(BitBoard::new(mirrored_boards[player_idx][piece_idx])) {
+                    output ^= ZOBRIST_TABLE.pieces[player_idx][piece_idx][tile.index()]
+                }
+            }
+        }
+        if let Some(en_passant_data) = &self.record.en_passant_data {
+            output ^= ZOBRIST_TABLE.en_passant[mirror(en_passant_data.passed_tile).index()]
+        }
+        if self.active_player == Color::Black {
+            output ^= ZOBRIST_TABLE.black_to_move
+        }
+        return output
+    }
+
     pub fn from_string(fen: String) -> Self {
         // fen format: <piece_info> <active_player> <source_tile_index,passed_tile_index,occupied_tile_index>
         let mut zobrist_hash = 0;
@@ -153,13 +286,59 @@ impl Position {
             "-" => PositionRecord::default(zobrist_hash),
             _ => PositionRecord::from_string(components[2].to_string(), zobrist_hash)
         };
-        Self { active_player, pieces, record: record.into() }
+        // Only a FEN that accounts for every tile says which board it is on, see from_string_on otherwise
+        let board_kind = BoardKind::from_num_tiles(Self::count_tiles(components[0]));
+        Self { active_player, pieces, record: record.into(), board_kind, zobrist_history: vec![], exact_history: None }
+    }
+
+    fn count_tiles(piece_info: &str) -> usize {
+        // Every piece is one tile and every run of digits is that many empty tiles
+        let mut tile_counter = 0;
+        let mut skip_tiles = "".to_string();
+        for symbol in piece_info.chars() {
+            match symbol.is_numeric() {
+                true => skip_tiles.push(symbol),
+                false => {
+                    if skip_tiles.len() > 0 {
+                        tile_counter += skip_tiles.parse::<usize>().unwrap();
+                        skip_tiles = "".to_string();
+                    }
+                    tile_counter += 1;
+                }
+            }
+        }
+        if skip_tiles.len() > 0 {
+            tile_counter += skip_tiles.parse::<usize>().unwrap();
+        }
+        return tile_counter
+    }
+
+    pub fn from_board_string(fen: String, board_kind: BoardKind) -> Result<Self, PositionError> {
+        // Unlike from_string, the piece info must account for every tile, trailing empties included
+        let tile_count = Self::count_tiles(fen.split(" ").next().unwrap_or(""));
+        if tile_count != board_kind.num_tiles() {
+            return Err(PositionError::TileCountMismatch(tile_count))
+        }
+        let mut position = Self::from_string(fen);
+        position.board_kind = Some(board_kind);
+        return Ok(position)
+    }
+
+    pub fn from_string_on(fen: String, board_kind: BoardKind) -> Result<Self, PositionError> {
+        // Like from_board_string, but trailing empty tiles may be left off
+        let tile_count = Self::count_tiles(fen.split(" ").next().unwrap_or(""));
+        if tile_count > board_kind.num_tiles() {
+            return Err(PositionError::TileCountMismatch(tile_count))
+        }
+        let mut position = Self::from_string(fen);
+        position.board_kind = Some(board_kind);
+        return Ok(position)
     }
 
     pub fn to_string(&self) -> String {
         let mut output = "".to_string();
         let mut empty_tile_counter = 0;
-        for tile in 0..MAX_NUM_TILES {
+        for tile in 0..self.num_tiles() {
             let tile_index = TileIndex::new(tile);
             if let Some(piece) = self.pieces[0].get_piece_at(&tile_index) {
                 let symbol = match piece {
@@ -193,6 +372,10 @@ impl Position {
                 empty_tile_counter += 1;
             }
         }
+        // Trailing empty tiles are written too, so the string says how big the board is, when that is known
+        if empty_tile_counter > 0 && self.board_kind.is_some() {
+            output.push_str(&empty_tile_counter.to_string());
+        }
         output.push(' ');
         match self.active_player {
             Color::White => output.push('w'),
@@ -210,15 +393,48 @@ impl Position {
     }
 
     pub fn new_traditional() -> Self {
-        return Position::from_string("RNBQKBNRPPPPPPPP32pppppppprnbqkbnr w -".to_string())
+        return Position::from_board_string(BoardKind::Traditional.starting_fen().to_string(), BoardKind::Traditional).unwrap()
+    }
+
+    pub fn new_chess960(seed: u64) -> Self {
+        // Back rank placed in the usual 960 order: bishops, queen, knights, then rook-king-rook in what is left
+        let mut rng = StdRng::seed_from_u64(seed);
+        let mut back_rank = [' '; 8];
+        back_rank[2 * rng.gen_range(0..4)] = 'B'; // Dark tiles (a1 is dark)
+        back_rank[2 * rng.gen_range(0..4) + 1] = 'B'; // Light tiles
+        for piece in ['Q', 'N', 'N'] {
+            let empty_files: Vec<usize> = (0..8).filter(|file| back_rank[*file] == ' ').collect();
+            back_rank[empty_files[rng.gen_range(0..empty_files.len())]] = piece;
+        }
+        for piece in ['R', 'K', 'R'] {
+            let first_empty = back_rank.iter().position(|square| *square == ' ').unwrap();
+            back_rank[first_empty] = piece;
+        }
+        let white_rank: String = back_rank.iter().collect();
+        let fen = format!("{}PPPPPPPP32pppppppp{} w -", white_rank, white_rank.to_lowercase());
+        return Position::from_string(fen)
+    }
+
+    pub fn random(board_kind: BoardKind, seed: u64, num_random_moves: usize, move_tables: &MoveTables) -> Self {
+        // Random legal moves from the starting position, stopping early if the game ends, for fuzz tests
+        let mut rng = StdRng::seed_from_u64(seed);
+        let mut position = board_kind.initial_position();
+        for _ in 0..num_random_moves {
+            let legal_moves = move_tables.get_legal_moves(&mut position);
+            if legal_moves.is_empty() {
+                break
+            }
+            position.make_legal_move(&legal_moves[rng.gen_range(0..legal_moves.len())]);
+        }
+        return position
     }
 
     pub fn new_hexagonal() -> Self {
-        return Position::from_string("BKNRP1QB2P2N1B1P3R3P4PPPPP21ppppp4p3r3p1b1n2p2bq1prnkb w -".to_string())
+        return Position::from_board_string(BoardKind::Hexagonal.starting_fen().to_string(), BoardKind::Hexagonal).unwrap()
     }
 
     pub fn new_triangular() -> Self {
-        return Position::from_string("RKNP6QBP6NP6P17ppppnbnqkr w -".to_string())
+        return Position::from_board_string(BoardKind::Triangular.starting_fen().to_string(), BoardKind::Triangular).unwrap()
     }
 
     pub fn is_in_check(&self, move_tables: &MoveTables, color: &Color) -> bool {
@@ -230,9 +446,7 @@ impl Position {
 
         // Orthogonals
         for rev_direction_table in move_tables.reverse_slide_tables.iter().step_by(2) {
-            let candidates = rev_direction_table[king_tile] & (
-                self.pieces[opponent_idx].piece_boards[PieceType::Rook.as_idx()] | self.pieces[opponent_idx].piece_boards[PieceType::Queen.as_idx()]
-            );
+            let candidates = rev_direction_table[king_tile] & self.pieces[opponent_idx].orthogonal_sliders();
             for candidate in BitBoardTiles::new(candidates) {
                 if move_tables.slide_tables.query(&candidate, &all_occupants, true, false).get_bit_at_tile(&king_tile) {
                     return true
@@ -242,9 +456,7 @@ impl Position {
        
         // Diagonals
         for rev_direction_table in move_tables.reverse_slide_tables.iter().skip(1).step_by(2) {
-            let candidates = rev_direction_table[king_tile] & (
-                self.pieces[opponent_idx].piece_boards[PieceType::Bishop.as_idx()] | self.pieces[opponent_idx].piece_boards[PieceType::Queen.as_idx()]
-            );
+            let candidates = rev_direction_table[king_tile] & self.pieces[opponent_idx].diagonal_sliders();
             for candidate in BitBoardTiles::new(candidates) {
                 if move_tables.slide_tables.query(&candidate, &all_occupants, false, true).get_bit_at_tile(&king_tile) {
                     return true
@@ -269,6 +481,128 @@ impl Position {
         false // Don't need to check for King-to-King threats
     }
 
+    pub fn attackers_of(&self, tile: &TileIndex, attacking_color: &Color, move_tables: &MoveTables) -> BitBoard {
+        // Uses the reverse tables like is_in_check, but collects every attacker instead of stopping at the first
+        let attacking_pieces = &self.pieces[attacking_color.as_idx()];
+        let all_occupants = self.get_occupied();
+        let mut attackers = BitBoard::empty();
+
+        let orthogonal_sliders = attacking_pieces.orthogonal_sliders();
+        for rev_direction_table in move_tables.reverse_slide_tables.iter().step_by(2) {
+            for candidate in BitBoardTiles::new(rev_direction_table[*tile] & orthogonal_sliders) {
+                if move_tables.slide_tables.query(&candidate, &all_occupants, true, false).get_bit_at_tile(tile) {
+                    attackers.flip_bit_at_tile_index(candidate);
+                }
+            }
+        }
+
+        let diagonal_sliders = attacking_pieces.diagonal_sliders();
+        for rev_direction_table in move_tables.reverse_slide_tables.iter().skip(1).step_by(2) {
+            for candidate in BitBoardTiles::new(rev_direction_table[*tile] & diagonal_sliders) {
+                if move_tables.slide_tables.query(&candidate, &all_occupants, false, true).get_bit_at_tile(tile) {
+                    attackers.flip_bit_at_tile_index(candidate);
+                }
+            }
+        }
+
+        attackers |= move_tables.reverse_knight_table[*tile] & attacking_pieces.piece_boards[PieceType::Knight.as_idx()];
+        attackers |= move_tables.king_table[*tile] & attacking_pieces.piece_boards[PieceType::King.as_idx()];
+        let pawn_threats = match attacking_color {
+            Color::White => &move_tables.reverse_white_pawn_table,
+            Color::Black => &move_tables.reverse_black_pawn_table
+        };
+        attackers |= pawn_threats[*tile] & attacking_pieces.piece_boards[PieceType::Pawn.as_idx()];
+        attackers
+    }
+
+    pub fn attack_map(&self, attacking_color: &Color, move_tables: &MoveTables) -> BitBoard {
+        // Every tile attacked by attacking_color. The defending king does not block slides,
+        // so tiles behind it along a slider's line still count as attacked (the king can't step back along a check)
+        let attacking_pieces = &self.pieces[attacking_color.as_idx()];
+        let defending_king = self.pieces[attacking_color.opponent().as_idx()].piece_boards[PieceType::King.as_idx()];
+        let occupied = self.get_occupied() & !defending_king;
+        let pawn_tables = match attacking_color {
+            Color::White => &move_tables.white_pawn_tables,
+            Color::Black => &move_tables.black_pawn_tables
+        };
+        let mut attacks = BitBoard::empty();
+
+        for piece_idx in 0..NUM_PIECE_TYPES {
+            let piece_type = PieceType::from_idx(piece_idx);
+            for source_tile in BitBoardTiles::new(attacking_pieces.piece_boards[piece_idx]) {
+                attacks |= match piece_type {
+                    PieceType::Pawn => pawn_tables.attack_table[source_tile],
+                    _ => move_tables.query_piece(&piece_type, source_tile, occupied)
+                };
+            }
+        }
+        attacks
+    }
+
+    pub fn pin_candidates(&self, color: &Color, move_tables: &MoveTables) -> BitBoard {
+        // Tiles on a line between color's king and an enemy slider that could move along it.
+        // Only pieces on these tiles can expose the king by moving, so it is a superset of the pinned pieces
+        let enemy_pieces = &self.pieces[color.opponent().as_idx()];
+        let king_tile = self.pieces[color.as_idx()].piece_boards[PieceType::King.as_idx()].lowest_one().unwrap();
+        let orthogonal_sliders = enemy_pieces.orthogonal_sliders();
+        let diagonal_sliders = enemy_pieces.diagonal_sliders();
+        let mut output = BitBoard::empty();
+
+        for (direction, rev_direction_table) in move_tables.reverse_slide_tables.iter().enumerate() {
+            let sliders = match direction % 2 {
+                0 => orthogonal_sliders,
+                _ => diagonal_sliders
+            };
+            if !(rev_direction_table[king_tile] & sliders).is_zero() {
+                output |= rev_direction_table[king_tile];
+            }
+        }
+        output
+    }
+
+    pub fn pins(&self, color: &Color, move_tables: &MoveTables) -> Vec<(TileIndex, BitBoard)> {
+        // Each of color's pinned pieces with the only tiles it can still move to,
+        // the line from the king up to and including the pinning slider
+        let enemy_pieces = &self.pieces[color.opponent().as_idx()];
+        let allied_occupants = self.pieces[color.as_idx()].occupied;
+        let king_tile = self.pieces[color.as_idx()].piece_boards[PieceType::King.as_idx()].lowest_one().unwrap();
+        let orthogonal_sliders = enemy_pieces.orthogonal_sliders();
+        let diagonal_sliders = enemy_pieces.diagonal_sliders();
+        let occupied = self.get_occupied();
+        let mut output = vec![];
+
+        for direction in 0..move_tables.slide_tables.0.len() {
+            let sliders = match direction % 2 {
+                0 => orthogonal_sliders,
+                _ => diagonal_sliders
+            };
+            // A slide ends on its first obstruction, so this is the nearest piece along the line
+            let pinned_tile = match (move_tables.slide_tables[direction][king_tile].get(&occupied) & allied_occupants).lowest_one() {
+                Some(tile) => tile,
+                None => continue
+            };
+            let mut behind_pinned = occupied;
+            behind_pinned.flip_bit_at_tile_index(pinned_tile);
+            let pin_ray = move_tables.slide_tables[direction][king_tile].get(&behind_pinned);
+            if !(pin_ray & sliders).is_zero() {
+                output.push((pinned_tile, pin_ray));
+            }
+        }
+        output
+    }
+
+    pub fn control_heatmap(&self, move_tables: &MoveTables) -> Vec<i32> {
+        // Positive values are controlled by White, negative values by Black
+        let mut output = vec![];
+        for tile_idx in 0..move_tables.king_table.num_tiles() {
+            let tile = TileIndex::new(tile_idx);
+            let white_attackers = self.attackers_of(&tile, &Color::White, move_tables).0.count_ones() as i32;
+            let black_attackers = self.attackers_of(&tile, &Color::Black, move_tables).0.count_ones() as i32;
+            output.push(white_attackers - black_attackers);
+        }
+        output
+    }
+
     pub fn is_checkmate(&mut self, move_tables: &MoveTables) -> bool {
         self.is_in_check(move_tables, &self.active_player) && !move_tables.has_legal_moves( self)
     }
@@ -277,8 +611,187 @@ impl Position {
         !self.is_in_check(move_tables, &self.active_player) && !move_tables.has_legal_moves(self)
     }
 
+    pub fn result(&mut self, move_tables: &MoveTables) -> Option<GameResult> {
+        // None while the game goes on
+        return self.game_over(move_tables).map(|game_over| game_over.result())
+    }
+
+    pub fn game_over(&mut self, move_tables: &MoveTables) -> Option<GameOver> {
+        // At checkmate the player to move is the one who lost
+        if self.is_checkmate(move_tables) {
+            return Some(GameOver::Checkmate(self.active_player.opponent()))
+        }
+        // Only automatic draws end the game, claimable ones are left to the players
+        return self.draw_reason(move_tables, 5, SEVENTY_FIVE_MOVE_PLIES).map(GameOver::Draw)
+    }
+
+    pub fn is_draw(&mut self, move_tables: &MoveTables) -> Option<DrawReason> {
+        // Drawn by the rules, counting threefold and fifty moves even though a player has to claim those
+        return self.draw_reason(move_tables, 3, FIFTY_MOVE_PLIES)
+    }
+
+    fn draw_reason(&mut self, move_tables: &MoveTables, repetitions: usize, move_plies: u32) -> Option<DrawReason> {
+        if self.is_stalemate(move_tables) {
+            return Some(DrawReason::Stalemate)
+        }
+        if self.record.fifty_move_counter >= move_plies {
+            return Some(DrawReason::FiftyMove)
+        }
+        if self.repetition_count() >= repetitions {
+            return Some(DrawReason::Repetition)
+        }
+        if self.is_insufficient_material() {
+            return Some(DrawReason::InsufficientMaterial)
+        }
+        None
+    }
+
+    pub fn draw_claim(&self) -> Option<DrawClaim> {
+        if self.repetition_draw() {
+            return Some(DrawClaim::ThreefoldRepetition)
+        }
+        if self.fifty_move_draw() {
+            return Some(DrawClaim::FiftyMoves)
+        }
+        None
+    }
+
     pub fn fifty_move_draw(&self) -> bool {
-        self.record.fifty_move_counter >= 50
+        self.record.fifty_move_counter >= FIFTY_MOVE_PLIES
+    }
+
+    pub fn is_insufficient_material(&self) -> bool {
+        // Neither side can force mate, the same test the searcher scores dead draws with
+        return !self.has_mating_material(&Color::White) && !self.has_mating_material(&Color::Black)
+    }
+
+    pub fn has_mating_material(&self, color: &Color) -> bool {
+        // A pawn could promote, and a queen, a rook or two minor pieces can force mate, but not two knights
+        let counts = self.piece_counts()[color.as_idx()];
+        let minor_pieces = counts[PieceType::Bishop.as_idx()] + counts[PieceType::Knight.as_idx()];
+        return counts[PieceType::Pawn.as_idx()] > 0
+            || counts[PieceType::Queen.as_idx()] > 0
+            || counts[PieceType::Rook.as_idx()] > 0
+            || (minor_pieces >= 2 && counts[PieceType::Bishop.as_idx()] > 0)
+    }
+
+    pub fn repetition_draw(&self) -> bool {
+        self.repetition_count() >= 3
+    }
+
+    pub fn repetition_eq(&self, other: &Position) -> bool {
+        // Same board, side to move and en passant tile, the counters don't matter for repetition
+        // Exact, unlike comparing zobrist keys which can collide
+        let passed_tile = |position: &Position| position.record.en_passant_data.as_ref().map(|data| data.passed_tile);
+        return self.active_player == other.active_player
+            && self.pieces[0].piece_boards == other.pieces[0].piece_boards
+            && self.pieces[1].piece_boards == other.pieces[1].piece_boards
+            && passed_tile(self) == passed_tile(other)
+    }
+
+    pub fn keep_exact_history(&mut self) {
+        // Positions from before this call are only ever matched by zobrist key
+        self.exact_history.get_or_insert_with(Vec::new);
+    }
+
+    fn snapshot(&self) -> Position {
+        // Built field by field, cloning self would copy both histories as well
+        return Position {
+            active_player: self.active_player,
+            pieces: self.pieces.clone(),
+            record: self.record.clone(),
+            board_kind: self.board_kind,
+            zobrist_history: vec![],
+            exact_history: None
+        }
+    }
+
+    pub fn repetition_count(&self) -> usize {
+        // How many times the current position has occurred, including now
+        // Only positions since the last irreversible move can repeat the current one
+        // A zobrist match is confirmed with repetition_eq when the earlier position was kept
+        let is_repeat = |(plies_ago, zobrist): (usize, &u64)| {
+            if *zobrist != self.record.zobrist {
+                return false
+            }
+            return match &self.exact_history {
+                Some(history) if plies_ago < history.len() => history[history.len() - 1 - plies_ago].repetition_eq(self),
+                _ => true
+            }
+        };
+        let repeats = self.zobrist_history.iter()
+            .rev()
+            .take(self.record.fifty_move_counter as usize)
+            .enumerate()
+            .filter(|&entry| is_repeat(entry))
+            .count();
+        return repeats + 1
+    }
+
+    pub fn en_passant_capture_tile(&self) -> Option<TileIndex> {
+        // The tile a pawn moves into to capture en passant, if the last move was a double push
+        self.record.en_passant_data.as_ref().map(|data| data.passed_tile)
+    }
+
+    pub fn material_balance(&self) -> isize {
+        // White minus Black in centipawns, kings left out, for displays rather than search
+        let piece_counts = self.piece_counts();
+        let mut balance = 0;
+        for piece_idx in 0..NUM_PIECE_TYPES {
+            if piece_idx == PieceType::King.as_idx() {
+                continue
+            }
+            let difference = piece_counts[0][piece_idx] as isize - piece_counts[1][piece_idx] as isize;
+            balance += difference * PIECE_SCORES[piece_idx];
+        }
+        balance
+    }
+
+    pub fn piece_counts(&self) -> [[u8; NUM_PIECE_TYPES]; 2] {
+        // Indexed by color then PieceType::as_idx
+        let mut counts = [[0; NUM_PIECE_TYPES]; 2];
+        for player_idx in 0..2 {
+            for piece_idx in 0..NUM_PIECE_TYPES {
+                counts[player_idx][piece_idx] = self.pieces[player_idx].piece_boards[piece_idx].0.count_ones() as u8;
+            }
+        }
+        counts
+    }
+
+    pub fn validate(&self, move_tables: &MoveTables) -> Result<(), Vec<PositionError>> {
+        // Catches states that can't arise in play, so a loaded position is safe to search from
+        let mut errors = vec![];
+        for tile in BitBoardTiles::new(self.get_occupied()) {
+            if tile.index() >= move_tables.king_table.num_tiles() {
+                errors.push(PositionError::PieceOffBoard(tile));
+            }
+        }
+        let piece_counts = self.piece_counts();
+        for color in [Color::White, Color::Black] {
+            let piece_set = &self.pieces[color.as_idx()];
+            if piece_counts[color.as_idx()][PieceType::King.as_idx()] != 1 {
+                errors.push(PositionError::WrongKingCount(color));
+            }
+            let promotion_board = match color {
+                Color::White => move_tables.white_pawn_tables.promotion_board,
+                Color::Black => move_tables.black_pawn_tables.promotion_board
+            };
+            for tile in BitBoardTiles::new(piece_set.piece_boards[PieceType::Pawn.as_idx()] & promotion_board) {
+                errors.push(PositionError::PawnOnPromotionTile(tile));
+            }
+        }
+        // is_in_check needs exactly one king per side on the board
+        if errors.is_empty() && self.is_in_check(move_tables, &self.active_player.opponent()) {
+            errors.push(PositionError::OpponentInCheck);
+        }
+        match errors.is_empty() {
+            true => Ok(()),
+            false => Err(errors)
+        }
+    }
+
+    pub fn legal_moves(&mut self, move_tables: &MoveTables) -> Vec<AnnotatedMove> {
+        move_tables.get_annotated_moves(self)
     }
 
     pub fn is_legal_move(&mut self, chess_move: &Move, move_tables: &MoveTables) -> bool {
@@ -294,20 +807,12 @@ impl Position {
    
     pub fn is_playable_move(&mut self, chess_move: &Move, move_tables: &MoveTables) -> bool {
         let player_idx = self.active_player.as_idx();
-        let opponent_idx = self.active_player.opponent().as_idx();
         let selected_piece = self.pieces[player_idx].get_piece_at(&chess_move.source_tile);
         
-        let movement_options = match selected_piece {
-            None => return false, // The moving player must have a piece at source_tile
-            Some(PieceType::Pawn) => move_tables.query_pawn(
-                &self.active_player,
-                chess_move.source_tile,
-                &self.pieces[opponent_idx].occupied,
-                self.get_occupied(),
-                &self.record.en_passant_data
-            ),
-            _ => move_tables.query_piece(&selected_piece.unwrap(), chess_move.source_tile, self.get_occupied())
-        };
+        if selected_piece == None {
+            return false // The moving player must have a piece at source_tile
+        }
+        let movement_options = move_tables.pseudo_destinations(self, chess_move.source_tile);
 
         if movement_options.get_bit_at_tile(&chess_move.destination_tile) == false {
             return false // The selected piece must be able to move to to_tile
@@ -326,6 +831,31 @@ impl Position {
         return true
     }
 
+    pub fn is_capture(&self, chess_move: &Move) -> bool {
+        // En passant is the only capture where the destination is empty
+        let player_idx = self.active_player.as_idx();
+        let opponent_idx = self.active_player.opponent().as_idx();
+        if self.pieces[opponent_idx].occupied.get_bit_at_tile(&chess_move.destination_tile) {
+            return true
+        }
+        match &self.record.en_passant_data {
+            Some(data) => {
+                data.passed_tile == chess_move.destination_tile
+                    && self.pieces[player_idx].get_piece_at(&chess_move.source_tile) == Some(PieceType::Pawn)
+            },
+            None => false
+        }
+    }
+
+    pub fn is_irreversible(&self, chess_move: &Move) -> bool {
+        // Pawn moves (including en passant and promotion) and captures can never be undone
+        // Must be called before the move is made
+        let player_idx = self.active_player.as_idx();
+        self.pieces[player_idx].get_piece_at(&chess_move.source_tile) == Some(PieceType::Pawn)
+            || chess_move.promotion.is_some()
+            || self.is_capture(chess_move)
+    }
+
     fn get_occupied(&self) -> BitBoard {
         return self.pieces[0].occupied | self.pieces[1].occupied
     }
@@ -336,47 +866,57 @@ impl Position {
         let opponent_idx = self.active_player.opponent().as_idx();
 
         let mut new_zobrist = self.record.zobrist;
+        self.zobrist_history.push(new_zobrist);
+        if self.exact_history.is_some() {
+            let snapshot = self.snapshot();
+            self.exact_history.as_mut().unwrap().push(snapshot);
+        }
 
         let source_tile = legal_move.source_tile;
         let destination_tile = legal_move.destination_tile;
 
-        let mut fifty_move_counter = self.record.fifty_move_counter + 1;
+        let fifty_move_counter = match self.is_irreversible(legal_move) {
+            true => 0,
+            false => self.record.fifty_move_counter + 1
+        };
 
-        let moving_piece = self.pieces[player_idx].get_piece_at(&source_tile).unwrap();
+        let moving_piece = self.active_pieces().get_piece_at(&source_tile).unwrap();
         new_zobrist ^= ZOBRIST_TABLE.pieces[player_idx][moving_piece.as_idx()][source_tile.index()];
         new_zobrist ^= ZOBRIST_TABLE.pieces[player_idx][moving_piece.as_idx()][destination_tile.index()];
-        self.pieces[player_idx].move_piece(source_tile, destination_tile);
+        self.active_pieces_mut().move_piece(source_tile, destination_tile);
 
-        let mut target_piece = self.pieces[opponent_idx].get_piece_at(&destination_tile);
+        let mut target_piece = self.enemy_pieces().get_piece_at(&destination_tile);
         if let Some(captured_piece) = target_piece {
-            fifty_move_counter = 0;
             new_zobrist ^= ZOBRIST_TABLE.pieces[opponent_idx][captured_piece.as_idx()][destination_tile.index()];
-            self.pieces[opponent_idx].capture_piece(destination_tile)
+            self.enemy_pieces_mut().capture_piece(destination_tile)
         };
 
         if let Some(promotion_target) =  &legal_move.promotion {
             new_zobrist ^= ZOBRIST_TABLE.pieces[player_idx][PieceType::Pawn.as_idx()][destination_tile.index()];
             new_zobrist ^= ZOBRIST_TABLE.pieces[player_idx][promotion_target.as_idx()][destination_tile.index()];
-            self.pieces[player_idx].promote_piece(destination_tile, promotion_target)
+            self.active_pieces_mut().promote_piece(destination_tile, promotion_target)
         }
 
         if moving_piece == PieceType::Pawn {
-            fifty_move_counter = 0;
             if let Some(en_passant_data) = &self.record.en_passant_data {
                 if destination_tile == en_passant_data.passed_tile {
                     target_piece = Some(PieceType::Pawn);
-                    self.pieces[opponent_idx].capture_piece(en_passant_data.occupied_tile)
+                    let occupied_tile = en_passant_data.occupied_tile;
+                    new_zobrist ^= ZOBRIST_TABLE.pieces[opponent_idx][PieceType::Pawn.as_idx()][occupied_tile.index()];
+                    self.enemy_pieces_mut().capture_piece(occupied_tile)
                 }
             }
         }
 
+        // En passant is hashed by the passed tile, matching get_zobrist
         if let Some(prev_en_passant_data) = &self.record.en_passant_data {
-            new_zobrist ^= ZOBRIST_TABLE.en_passant[prev_en_passant_data.source_tile.index()]
+            new_zobrist ^= ZOBRIST_TABLE.en_passant[prev_en_passant_data.passed_tile.index()]
         }
 
-        if legal_move.en_passant_data != None {
-            new_zobrist ^= ZOBRIST_TABLE.en_passant[source_tile.index()];
+        if let Some(next_en_passant_data) = &legal_move.en_passant_data {
+            new_zobrist ^= ZOBRIST_TABLE.en_passant[next_en_passant_data.passed_tile.index()];
         }
+        new_zobrist ^= ZOBRIST_TABLE.black_to_move;
 
         self.record = PositionRecord {
             en_passant_data: legal_move.en_passant_data.clone(),
@@ -386,28 +926,34 @@ impl Position {
             fifty_move_counter: fifty_move_counter
         }.into();
 
-        self.pieces[player_idx].update_occupied();
-        self.pieces[opponent_idx].update_occupied();
+        self.active_pieces_mut().update_occupied();
+        self.enemy_pieces_mut().update_occupied();
         self.active_player = self.active_player.opponent();
+        #[cfg(debug_assertions)]
+        for piece_set in &self.pieces {
+            piece_set.check_invariant();
+        }
     }
 
     pub fn unmake_legal_move(&mut self, legal_move: &Move) {
         // Assumes the move was legal
         self.active_player = self.active_player.opponent();
-        let player_idx = self.active_player.as_idx();
-        let opponent_idx = self.active_player.opponent().as_idx();
        
         let source_tile = legal_move.source_tile;
         let destination_tile = legal_move.destination_tile;
        
-        self.pieces[player_idx].move_piece(destination_tile, source_tile);
+        self.active_pieces_mut().move_piece(destination_tile, source_tile);
 
         let captured_piece = self.record.captured_piece.to_owned();
         if let Some(ref piece_type) = captured_piece {
-            self.pieces[opponent_idx].return_piece(destination_tile, &piece_type)
+            self.enemy_pieces_mut().return_piece(destination_tile, &piece_type)
         }
         if let Some(_t) = &legal_move.promotion {
-            self.pieces[player_idx].demote_piece(source_tile)
+            self.active_pieces_mut().demote_piece(source_tile)
+        }
+        self.zobrist_history.pop();
+        if let Some(history) = &mut self.exact_history {
+            history.pop();
         }
         if let Some(prev_record) = self.record.get_previous_record() {
             self.record = prev_record
@@ -417,13 +963,18 @@ impl Position {
         if captured_piece == Some(PieceType::Pawn) {
             if let Some(en_passant_data) = &self.record.en_passant_data {
                 if destination_tile == en_passant_data.passed_tile {
-                    self.pieces[opponent_idx].capture_piece(destination_tile);
-                    self.pieces[opponent_idx].return_piece(en_passant_data.occupied_tile, &PieceType::Pawn)
+                    let occupied_tile = en_passant_data.occupied_tile;
+                    self.enemy_pieces_mut().capture_piece(destination_tile);
+                    self.enemy_pieces_mut().return_piece(occupied_tile, &PieceType::Pawn)
                 }
             }
         }
-        self.pieces[player_idx].update_occupied();
-        self.pieces[opponent_idx].update_occupied();
+        self.active_pieces_mut().update_occupied();
+        self.enemy_pieces_mut().update_occupied();
+        #[cfg(debug_assertions)]
+        for piece_set in &self.pieces {
+            piece_set.check_invariant();
+        }
     }
 }
 
@@ -432,8 +983,18 @@ impl Position {
 mod tests {
     use super::*;
     use crate::bit_board::BitBoard;
+    use crate::graph_boards::board_kind::BoardKind;
     use crate::graph_boards::traditional_board::TraditionalBoardGraph;
 
+    #[test]
+    fn test_active_pieces() {
+        let mut position = Position::new_traditional();
+        assert_eq!(position.active_pieces().occupied, position.pieces[0].occupied);
+        assert_eq!(position.enemy_pieces().occupied, position.pieces[1].occupied);
+        position.make_legal_move(&Move::new(TileIndex::new(12), TileIndex::new(28), None, None));
+        assert_eq!(position.active_pieces().occupied, position.pieces[1].occupied)
+    }
+
     #[test]
     fn test_new_traditional_occupied() {
         let position = Position::new_traditional();
@@ -465,6 +1026,246 @@ mod tests {
         )
     }
 
+    #[test]
+    fn test_new_chess960() {
+        for seed in 0..20 {
+            let position = Position::new_chess960(seed);
+            let white = &position.pieces[0].piece_boards;
+            let black = &position.pieces[1].piece_boards;
+            assert_eq!(
+                position.piece_counts(),
+                Position::new_traditional().piece_counts()
+            );
+            let king_file = white[PieceType::King.as_idx()].lowest_one().unwrap().index();
+            let rook_files: Vec<usize> = BitBoardTiles::new(white[PieceType::Rook.as_idx()]).map(|tile| tile.index()).collect();
+            assert!(rook_files[0] < king_file && king_file < rook_files[1]);
+            let bishop_files: Vec<usize> = BitBoardTiles::new(white[PieceType::Bishop.as_idx()]).map(|tile| tile.index()).collect();
+            assert_ne!(
+                bishop_files[0] % 2,
+                bishop_files[1] % 2
+            );
+            for piece_idx in 0..NUM_PIECE_TYPES { // Black mirrors White across the board
+                assert_eq!(
+                    black[piece_idx].0 & 0xFFFF_FFFF_FFFF_FFFF,
+                    ((white[piece_idx].0 as u64).swap_bytes()) as u128
+                )
+            }
+        }
+        assert_ne!( // Different seeds give different back ranks
+            (0..20).map(|seed| Position::new_chess960(seed).get_zobrist()).collect::<std::collections::HashSet<u64>>().len(),
+            1
+        )
+    }
+
+    #[test]
+    fn test_result() {
+        let move_tables = test_move_tables();
+        // White king on h1 mated by the rooks on a1 and a2
+        let mut white_mated = Position::from_string("r6K1r53k w -".to_string());
+        assert_eq!(
+            white_mated.result(&move_tables),
+            Some(GameResult::BlackWins)
+        );
+        // Black king on h8 has no moves but is not in check from the queen on f7
+        let mut stalemate = Position::from_string("K52Q9k b -".to_string());
+        assert_eq!(
+            stalemate.result(&move_tables),
+            Some(GameResult::Draw)
+        );
+        assert_eq!(
+            Position::new_traditional().result(&move_tables),
+            None
+        )
+    }
+
+    #[test]
+    fn test_repetition_eq() {
+        let mut shuffled = Position::new_traditional();
+        for (source, destination) in [(1, 18), (57, 42), (18, 1), (42, 57)] {
+            shuffled.make_legal_move(&Move::new(TileIndex::new(source), TileIndex::new(destination), None, None));
+        }
+        assert_eq!(
+            shuffled.record.fifty_move_counter,
+            4
+        );
+        assert!(shuffled.repetition_eq(&Position::new_traditional()));
+
+        let with_en_passant = Position::from_string("r15N1b6P6KPp4r23k w 50,42,34".to_string());
+        let without_en_passant = Position::from_string("r15N1b6P6KPp4r23k w -".to_string());
+        assert!(!with_en_passant.repetition_eq(&without_en_passant))
+    }
+
+    #[test]
+    fn test_repetition_count() {
+        let mut position = Position::new_traditional();
+        assert_eq!(
+            position.repetition_count(),
+            1
+        );
+        let knight_shuffle = [
+            Move::new(TileIndex::new(1), TileIndex::new(18), None, None),
+            Move::new(TileIndex::new(57), TileIndex::new(42), None, None),
+            Move::new(TileIndex::new(18), TileIndex::new(1), None, None),
+            Move::new(TileIndex::new(42), TileIndex::new(57), None, None)
+        ];
+        for chess_move in &knight_shuffle {
+            position.make_legal_move(chess_move);
+        }
+        assert_eq!(
+            position.repetition_count(),
+            2
+        );
+        assert!(!position.repetition_draw());
+        position.make_legal_move(&Move::new(TileIndex::new(12), TileIndex::new(20), None, None)); // A new position
+        assert_eq!(
+            position.repetition_count(),
+            1
+        )
+    }
+
+    #[test]
+    fn test_zobrist_history() {
+        let mut position = Position::new_traditional();
+        let knight_shuffle = [
+            Move::new(TileIndex::new(6), TileIndex::new(21), None, None),
+            Move::new(TileIndex::new(62), TileIndex::new(45), None, None),
+            Move::new(TileIndex::new(21), TileIndex::new(6), None, None),
+            Move::new(TileIndex::new(45), TileIndex::new(62), None, None)
+        ];
+        for _ in 0..2 {
+            for chess_move in &knight_shuffle {
+                position.make_legal_move(chess_move);
+            }
+        }
+        assert_eq!(
+            position.zobrist_history.len(),
+            8
+        );
+        assert_eq!(
+            position.repetition_count(),
+            3
+        );
+        assert_eq!(position.draw_claim(), Some(DrawClaim::ThreefoldRepetition));
+
+        // Unmaking pops the history, so a search leaves it as it found it
+        position.unmake_legal_move(&knight_shuffle[3]);
+        assert_eq!(
+            position.zobrist_history.len(),
+            7
+        );
+        assert_eq!(
+            position.repetition_count(),
+            2
+        );
+        assert_eq!(position.zobrist_history.last(), Some(&position.record.get_previous_record().unwrap().zobrist))
+    }
+
+    #[test]
+    fn test_exact_history_rejects_zobrist_collisions() {
+        let knight_shuffle = [(6, 21), (62, 45), (21, 6), (45, 62)];
+        let mut position = Position::new_traditional();
+        position.keep_exact_history();
+        for (source, destination) in knight_shuffle {
+            position.make_legal_move(&Move::new(TileIndex::new(source), TileIndex::new(destination), None, None));
+        }
+        assert_eq!(
+            position.exact_history.as_ref().unwrap().len(),
+            4
+        );
+        assert_eq!(
+            position.repetition_count(),
+            2
+        );
+
+        // A collision with a different board is only counted by the zobrist key
+        let current_zobrist = position.record.zobrist;
+        position.zobrist_history[2] = current_zobrist;
+        assert_eq!(
+            position.repetition_count(),
+            2
+        );
+        let mut unchecked = position.clone();
+        unchecked.exact_history = None;
+        assert_eq!(
+            unchecked.repetition_count(),
+            3
+        );
+
+        position.unmake_legal_move(&Move::new(TileIndex::new(45), TileIndex::new(62), None, None));
+        assert_eq!(
+            position.exact_history.as_ref().unwrap().len(),
+            3
+        )
+    }
+
+    #[test]
+    fn test_has_mating_material() {
+        for (fen, white_can_mate) in [
+            ("4K55k3 w -", false),
+            ("4K22N32k3 w -", false),
+            ("4K22B32k3 w -", false),
+            ("4K21NN32k3 w -", false),
+            ("4K21BB32k3 w -", true),
+            ("4K21BN32k3 w -", true),
+            ("4K22R32k3 w -", true),
+            ("4K22P32k3 w -", true)
+        ] {
+            let position = Position::from_string(fen.to_string());
+            assert_eq!(position.has_mating_material(&Color::White), white_can_mate, "{}", fen);
+            assert!(!position.has_mating_material(&Color::Black))
+        }
+    }
+
+    #[test]
+    fn test_canonical_zobrist() {
+        // A knight on d4 and its mirror image on e4, with the kings swapped to match
+        let position = Position::from_string("4K22N32k3 w -".to_string());
+        let mirrored = Position::from_string("3K24N30k4 w -".to_string());
+        assert_ne!(position.record.zobrist, mirrored.record.zobrist);
+        assert_eq!(position.canonical_zobrist(), mirrored.canonical_zobrist());
+        assert_ne!(position.canonical_zobrist(), Position::from_string("4K23N31k3 w -".to_string()).canonical_zobrist());
+
+        // The en passant tile is mirrored too
+        let en_passant = Position::from_string("4K30pP23k3 w 51,43,35".to_string());
+        let mirrored_en_passant = Position::from_string("3K31Pp22k4 w 52,44,36".to_string());
+        assert_eq!(en_passant.canonical_zobrist(), mirrored_en_passant.canonical_zobrist());
+
+        // The start position only differs from its mirror by the kings and queens
+        let mirrored_start = Position::from_string("RNBKQBNRPPPPPPPP32pppppppprnbkqbnr w -".to_string());
+        assert_eq!(Position::new_traditional().canonical_zobrist(), mirrored_start.canonical_zobrist());
+
+        // Boards without the symmetry keep the plain key
+        let hexagonal = Position::new_hexagonal();
+        assert_eq!(hexagonal.canonical_zobrist(), hexagonal.record.zobrist)
+    }
+
+    #[test]
+    fn test_piece_counts() {
+        // King, Queen, Rook, Bishop, Knight, Pawn
+        assert_eq!(
+            Position::new_traditional().piece_counts(),
+            [[1, 1, 2, 2, 2, 8], [1, 1, 2, 2, 2, 8]]
+        );
+        assert_eq!(
+            Position::from_string("4K31P12P1p11k b -".to_string()).piece_counts(),
+            [[1, 0, 0, 0, 0, 2], [1, 0, 0, 0, 0, 1]]
+        )
+    }
+
+    #[test]
+    fn test_new_triangular_occupied() {
+        // The last black rook sits on tile 54, so the FEN covers exactly the 55 triangular tiles
+        let position: Position = Position::new_triangular();
+        let occupied = position.pieces[0].occupied | position.pieces[1].occupied;
+        assert_eq!(
+            occupied,
+            BitBoard::from_ints(vec![
+                0, 1, 2, 3, 10, 11, 12, 19, 20, 27,
+                45, 46, 47, 48, 49, 50, 51, 52, 53, 54
+            ])
+        )
+    }
+
     #[test]
     fn test_make_legal_move() {
         let mut position = Position::new_traditional();
@@ -613,6 +1414,167 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_is_irreversible() {
+        let position = Position::from_string("K4P12N16pP15P10k w 51,43,35".to_string());
+        assert_eq!( // Pawn push
+            position.is_irreversible(&Move::new(TileIndex::new(5), TileIndex::new(13), None, None)),
+            true
+        );
+        assert_eq!( // En passant
+            position.is_irreversible(&Move::new(TileIndex::new(36), TileIndex::new(43), None, None)),
+            true
+        );
+        assert_eq!( // Promotion
+            position.is_irreversible(&Move::new(TileIndex::new(52), TileIndex::new(60), Some(PieceType::Queen), None)),
+            true
+        );
+        assert_eq!( // Capture
+            position.is_irreversible(&Move::new(TileIndex::new(18), TileIndex::new(35), None, None)),
+            true
+        );
+        assert_eq!( // Knight move
+            position.is_irreversible(&Move::new(TileIndex::new(18), TileIndex::new(1), None, None)),
+            false
+        );
+    }
+
+    #[test]
+    fn test_fifty_move_counter_reset() {
+        let mut position = Position::new_traditional();
+        position.make_legal_move(&Move::new(TileIndex::new(6), TileIndex::new(21), None, None));
+        position.make_legal_move(&Move::new(TileIndex::new(57), TileIndex::new(42), None, None));
+        assert_eq!(position.record.fifty_move_counter, 2); // Knight moves are reversible
+        position.make_legal_move(&Move::new(TileIndex::new(21), TileIndex::new(36), None, None));
+        position.make_legal_move(&Move::new(TileIndex::new(42), TileIndex::new(36), None, None));
+        assert_eq!(position.record.fifty_move_counter, 0); // Capture
+        position.make_legal_move(&Move::new(TileIndex::new(1), TileIndex::new(18), None, None));
+        assert_eq!(position.record.fifty_move_counter, 1);
+        position.make_legal_move(&Move::new(TileIndex::new(48), TileIndex::new(40), None, None));
+        assert_eq!(position.record.fifty_move_counter, 0); // Pawn push
+    }
+
+    #[test]
+    fn test_repetition_draw() {
+        let mut position = Position::new_traditional();
+        let knight_moves = [
+            Move::new(TileIndex::new(6), TileIndex::new(21), None, None),
+            Move::new(TileIndex::new(62), TileIndex::new(45), None, None),
+            Move::new(TileIndex::new(21), TileIndex::new(6), None, None),
+            Move::new(TileIndex::new(45), TileIndex::new(62), None, None)
+        ];
+        for chess_move in &knight_moves {
+            position.make_legal_move(chess_move);
+        }
+        assert_eq!(position.repetition_draw(), false); // Second occurrence
+        for chess_move in &knight_moves {
+            position.make_legal_move(chess_move);
+        }
+        assert_eq!(position.repetition_draw(), true) // Third occurrence
+    }
+
+    #[test]
+    fn test_is_draw() {
+        let move_tables = BoardKind::Traditional.move_tables();
+        let mut stalemate = Position::from_string("4K45Q5k7 b -".to_string());
+        assert_eq!(stalemate.is_draw(&move_tables), Some(DrawReason::Stalemate));
+
+        let mut fifty_moves = Position::new_traditional();
+        assert_eq!(fifty_moves.is_draw(&move_tables), None);
+        Arc::get_mut(&mut fifty_moves.record).unwrap().fifty_move_counter = FIFTY_MOVE_PLIES;
+        assert_eq!(fifty_moves.is_draw(&move_tables), Some(DrawReason::FiftyMove));
+
+        let mut repetition = Position::new_traditional();
+        for _ in 0..2 {
+            for (source, destination) in [(6, 21), (62, 45), (21, 6), (45, 62)] {
+                repetition.make_legal_move(&Move::new(TileIndex::new(source), TileIndex::new(destination), None, None));
+            }
+        }
+        assert_eq!(repetition.is_draw(&move_tables), Some(DrawReason::Repetition));
+        assert_eq!(repetition.game_over(&move_tables), None); // Threefold still has to be claimed
+
+        let mut bare_kings = Position::from_string("4K55k3 w -".to_string());
+        assert_eq!(bare_kings.is_draw(&move_tables), Some(DrawReason::InsufficientMaterial));
+        assert_eq!(
+            bare_kings.game_over(&move_tables),
+            Some(GameOver::Draw(DrawReason::InsufficientMaterial))
+        );
+        for fen in ["4K1N53k3 w -", "4KB54k3 w -"] {
+            let mut lone_minor = Position::from_string(fen.to_string());
+            assert_eq!(lone_minor.is_draw(&move_tables), Some(DrawReason::InsufficientMaterial));
+        }
+        let mut lone_rook = Position::from_string("4KR54k3 w -".to_string());
+        assert_eq!(lone_rook.is_draw(&move_tables), None)
+    }
+
+    #[test]
+    fn test_random_positions() {
+        for board_kind in [BoardKind::Traditional, BoardKind::Hexagonal, BoardKind::Triangular] {
+            let move_tables = board_kind.move_tables();
+            for seed in 0..10 {
+                let position = Position::random(board_kind, seed, 40, &move_tables);
+                assert_eq!(position.validate(&move_tables), Ok(()));
+                let again = Position::random(board_kind, seed, 40, &move_tables);
+                assert_eq!(position.to_string(), again.to_string());
+                assert_eq!(position.record.zobrist, again.record.zobrist);
+            }
+        }
+    }
+
+    #[test]
+    fn test_material_balance() {
+        assert_eq!(Position::new_traditional().material_balance(), 0);
+        // White is missing the knight on b1
+        assert_eq!(
+            Position::from_string("R1BQKBNRPPPPPPPP32pppppppprnbqkbnr b -".to_string()).material_balance(),
+            -350
+        )
+    }
+
+    #[test]
+    fn test_constructors_set_board_kind() {
+        for (position, board_kind) in [
+            (Position::new_traditional(), BoardKind::Traditional),
+            (Position::new_hexagonal(), BoardKind::Hexagonal),
+            (Position::new_triangular(), BoardKind::Triangular)
+        ] {
+            assert_eq!(position.board_kind, Some(board_kind));
+            let written = position.to_string();
+            assert_eq!(
+                Position::count_tiles(written.split(" ").next().unwrap()),
+                board_kind.num_tiles()
+            );
+        }
+        // Trailing empty tiles are filled in when written back out, once the board is known
+        assert_eq!(
+            Position::from_string_on("4K55k w -".to_string(), BoardKind::Traditional).unwrap().to_string(),
+            "4K55k3 w -"
+        );
+        assert_eq!(
+            Position::from_string("4K55k w -".to_string()).to_string(),
+            "4K55k w -"
+        );
+        assert!(Position::from_string_on("4K55k4R w -".to_string(), BoardKind::Traditional).is_err())
+    }
+
+    #[test]
+    fn test_unknown_board_keeps_every_tile() {
+        // Without a stated board, a piece past tile 63 is still written out and hashed
+        let far_rook = Position::from_string("K4k74R w -".to_string());
+        let further_rook = Position::from_string("K4k75R w -".to_string());
+        assert_eq!(far_rook.board_kind, None);
+        assert_eq!(
+            far_rook.to_string(),
+            "K4k74R w -"
+        );
+        assert_eq!(
+            Position::from_string(far_rook.to_string()).get_zobrist(),
+            far_rook.get_zobrist()
+        );
+        assert_ne!(far_rook.get_zobrist(), further_rook.get_zobrist());
+        assert_eq!(far_rook.get_zobrist(), far_rook.record.zobrist)
+    }
+
     #[test]
     fn test_string_conversion() {
         let position = Position::new_traditional();
@@ -681,6 +1643,200 @@ mod tests {
         ); // White in check by unblocked diagonal queen
     }
 
+    #[test]
+    fn test_attackers_of() {
+        let position = Position::new_traditional();
+        let move_tables = test_move_tables();
+        assert_eq!( // f3 is covered by the e and g pawns and the g1 knight
+            position.attackers_of(&TileIndex::new(21), &Color::White, &move_tables),
+            BitBoard::from_ints(vec![6, 12, 14])
+        );
+        assert_eq!(
+            position.attackers_of(&TileIndex::new(21), &Color::Black, &move_tables),
+            BitBoard::empty()
+        )
+    }
+
+    #[test]
+    fn test_control_heatmap() {
+        let position = Position::new_traditional();
+        let move_tables = test_move_tables();
+        let heatmap = position.control_heatmap(&move_tables);
+        assert_eq!(heatmap.len(), 64);
+        for tile in 0..32 {
+            assert!(heatmap[tile] >= 0);
+            assert_eq!(heatmap[tile], -heatmap[(7 - tile / 8) * 8 + tile % 8]); // Mirrored for Black
+        }
+        for tile in 16..24 {
+            assert!(heatmap[tile] > 0)
+        }
+    }
+
+    #[test]
+    fn test_en_passant_capture_tile() {
+        let mut position = Position::new_traditional();
+        assert_eq!(
+            position.en_passant_capture_tile(),
+            None
+        );
+        position.make_legal_move(&Move::new(TileIndex::new(12), TileIndex::new(28), None, Some(TileIndex::new(20))));
+        assert_eq!(
+            position.en_passant_capture_tile(),
+            Some(TileIndex::new(20))
+        );
+        position.make_legal_move(&Move::new(TileIndex::new(62), TileIndex::new(45), None, None));
+        assert_eq!(
+            position.en_passant_capture_tile(),
+            None
+        )
+    }
+
+    #[test]
+    fn test_validate_initial_position() {
+        assert_eq!(
+            Position::new_traditional().validate(&test_move_tables()),
+            Ok(())
+        )
+    }
+
+    #[test]
+    fn test_validate_two_white_kings() {
+        let position = Position::from_string("K1K60k w -".to_string());
+        assert_eq!(
+            position.validate(&test_move_tables()),
+            Err(vec![PositionError::WrongKingCount(Color::White)])
+        )
+    }
+
+    #[test]
+    fn test_from_board_string_missing_rank() {
+        let position = Position::from_board_string(
+            "RNBQKBNRPPPPPPPP24pppppppprnbqkbnr w -".to_string(),
+            BoardKind::Traditional
+        );
+        assert_eq!(
+            position.err(),
+            Some(PositionError::TileCountMismatch(56))
+        )
+    }
+
+    #[test]
+    fn test_from_board_string_exact_count() {
+        let position = Position::from_board_string(
+            "RNBQKBNRPPPPPPPP32pppppppprnbqkbnr w -".to_string(),
+            BoardKind::Traditional
+        ).unwrap();
+        assert_eq!(
+            position.to_string(),
+            Position::new_traditional().to_string()
+        );
+        assert!(Position::from_board_string("4K55k3 w -".to_string(), BoardKind::Traditional).is_ok())
+    }
+
+    #[test]
+    fn test_validate_opponent_in_check() {
+        // Black is in check from the rook on h1, but it is White's turn
+        let position = Position::from_string("K6R55k w -".to_string());
+        assert_eq!(
+            position.validate(&test_move_tables()),
+            Err(vec![PositionError::OpponentInCheck])
+        )
+    }
+
+    #[test]
+    fn test_validate_pawn_on_last_rank() {
+        let position = Position::from_string("K55P6k w -".to_string());
+        assert_eq!(
+            position.validate(&test_move_tables()),
+            Err(vec![PositionError::PawnOnPromotionTile(TileIndex::new(56))])
+        )
+    }
+
+    #[test]
+    fn test_validate_piece_off_board() {
+        // Valid on the traditional board, but the triangular board only has 55 tiles
+        let position = Position::from_string("K61k w -".to_string());
+        assert_eq!(
+            position.validate(&BoardKind::Triangular.move_tables()),
+            Err(vec![PositionError::PieceOffBoard(TileIndex::new(62))])
+        )
+    }
+
+    #[test]
+    fn test_legal_moves_annotations() {
+        let mut position = Position::new_traditional();
+        let move_tables = test_move_tables();
+        let annotated_moves = position.legal_moves(&move_tables);
+        assert_eq!(
+            annotated_moves.len(),
+            20
+        );
+        for annotated_move in annotated_moves {
+            assert!(!annotated_move.is_capture);
+            assert!(!annotated_move.gives_check);
+            assert!(!annotated_move.is_promotion);
+        }
+    }
+
+    #[test]
+    fn test_legal_moves_gives_check() {
+        // Rg1 and Re8 are the only moves that check the king on g8
+        let mut position = Position::from_string("4R2K54k1 w -".to_string());
+        let move_tables = test_move_tables();
+        let checking_moves: Vec<Move> = position.legal_moves(&move_tables).into_iter()
+            .filter(|annotated_move| annotated_move.gives_check)
+            .map(|annotated_move| annotated_move.chess_move)
+            .collect();
+        assert_eq!(
+            checking_moves,
+            vec![
+                Move::new(TileIndex::new(4), TileIndex::new(6), None, None),
+                Move::new(TileIndex::new(4), TileIndex::new(60), None, None)
+            ]
+        )
+    }
+
+    #[test]
+    fn test_attack_map_sees_through_king() {
+        // Black rook on e8 checks the White king on e4, e3 behind the king is still attacked
+        let position = Position::from_string("28K27k3r3 w -".to_string());
+        let move_tables = test_move_tables();
+        let attacks = position.attack_map(&Color::Black, &move_tables);
+        assert!(attacks.get_bit_at_tile(&TileIndex::new(28)));
+        assert!(attacks.get_bit_at_tile(&TileIndex::new(20)));
+        assert!(!attacks.get_bit_at_tile(&TileIndex::new(29)))
+    }
+
+    #[test]
+    fn test_pin_candidates() {
+        // White knight on e2 is pinned to the king on e1 by the rook on e8
+        let position = Position::from_string("4K7N47r2k w -".to_string());
+        let move_tables = test_move_tables();
+        assert_eq!(
+            position.pin_candidates(&Color::White, &move_tables),
+            BitBoard::from_ints(vec![12, 20, 28, 36, 44, 52, 60])
+        );
+        assert_eq!(
+            position.pin_candidates(&Color::Black, &move_tables),
+            BitBoard::empty()
+        )
+    }
+
+    #[test]
+    fn test_pins() {
+        // The knight on e2 is pinned by the rook on e8, the rook on d1 is not pinned at all
+        let position = Position::from_string("3RK7N47r2k w -".to_string());
+        let move_tables = test_move_tables();
+        assert_eq!(
+            position.pins(&Color::White, &move_tables),
+            vec![(TileIndex::new(12), BitBoard::from_ints(vec![12, 20, 28, 36, 44, 52, 60]))]
+        );
+        assert_eq!(
+            position.pins(&Color::Black, &move_tables),
+            vec![]
+        )
+    }
+
     #[test]
     fn test_zobrist_unmade_moves() {
         // Testing that prev_record stores the zobrist hash correctly
@@ -0,0 +1,138 @@
+use std::sync::{atomic::{AtomicBool, Ordering}, Arc};
+use std::thread::{self, JoinHandle};
+
+use crate::{position::Position, searcher::{SearchInfo, SearchResult, Searcher}};
+
+
+// Runs a search on a background thread so a caller like the GUI can keep going and check back each frame
+pub struct SearchHandle {
+    stop_signal: Arc<AtomicBool>,
+    thread: Option<JoinHandle<(Searcher, SearchResult)>>,
+    searcher: Option<Searcher>,
+    result: Option<SearchResult>
+}
+
+impl SearchHandle {
+    pub fn spawn(searcher: Searcher, position: Position, max_depth: u8, time_ms: Option<u64>) -> Self {
+        // The position is the searcher's own copy, the caller's is never touched
+        return Self::spawn_reporting(searcher, position, max_depth, time_ms, |_info| {}, |_result| {})
+    }
+
+    pub fn spawn_reporting(
+        mut searcher: Searcher,
+        mut position: Position,
+        max_depth: u8,
+        time_ms: Option<u64>,
+        mut on_info: impl FnMut(&SearchInfo) + Send + 'static,
+        on_done: impl FnOnce(&SearchResult) + Send + 'static
+    ) -> Self {
+        // on_info runs on the search thread after each depth, and on_done once the search is over
+        let stop_signal = Arc::new(AtomicBool::new(false));
+        searcher.stop_signal = Some(stop_signal.clone());
+        let thread = thread::spawn(move || {
+            let result = searcher.search_with_info(&mut position, max_depth, time_ms, &mut on_info);
+            on_done(&result);
+            return (searcher, result)
+        });
+        return SearchHandle { stop_signal, thread: Some(thread), searcher: None, result: None }
+    }
+
+    pub fn poll(&mut self) -> Option<SearchResult> {
+        // None until the search is over, never blocks
+        if let Some(thread) = &self.thread {
+            if !thread.is_finished() {
+                return None
+            }
+            self.join();
+        }
+        return self.result.clone()
+    }
+
+    pub fn stop(&self) {
+        // The search finishes within a few thousand nodes, keeping the best move of the last full depth
+        self.stop_signal.store(true, Ordering::Relaxed);
+    }
+
+    pub fn into_searcher(mut self) -> Searcher {
+        // Stops the search if it is still going, so the searcher and its table can be reused
+        self.stop();
+        self.join();
+        let mut searcher = self.searcher.take().unwrap();
+        searcher.stop_signal = None;
+        return searcher
+    }
+
+    fn join(&mut self) {
+        if let Some(thread) = self.thread.take() {
+            let (searcher, result) = thread.join().expect("search thread panicked");
+            self.searcher = Some(searcher);
+            self.result = Some(result);
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::{Duration, Instant};
+    use crate::graph_boards::board_kind::BoardKind;
+
+    fn wait_for_result(handle: &mut SearchHandle, timeout: Duration) -> Option<SearchResult> {
+        let start = Instant::now();
+        loop {
+            if let Some(result) = handle.poll() {
+                return Some(result)
+            }
+            if start.elapsed() > timeout {
+                return None
+            }
+            thread::sleep(Duration::from_millis(5));
+        }
+    }
+
+    #[test]
+    fn test_spawned_search_finds_a_legal_move() {
+        let searcher = Searcher::new(BoardKind::Traditional.shared_move_tables());
+        let mut position = Position::new_traditional();
+        let mut handle = SearchHandle::spawn(searcher, position.clone(), 3, None);
+        let result = wait_for_result(&mut handle, Duration::from_secs(30)).expect("search never finished");
+        let best_move = result.best_move.unwrap();
+        let searcher = handle.into_searcher();
+        assert!(searcher.movegen.get_legal_moves(&mut position).contains(&best_move))
+    }
+
+    #[test]
+    fn test_reporting_streams_each_depth() {
+        let searcher = Searcher::new(BoardKind::Traditional.shared_move_tables());
+        let (sender, receiver) = std::sync::mpsc::channel();
+        let done_sender = sender.clone();
+        let mut handle = SearchHandle::spawn_reporting(
+            searcher,
+            Position::new_traditional(),
+            3,
+            None,
+            move |info| sender.send(Some(info.depth)).unwrap(),
+            move |_result| done_sender.send(None).unwrap()
+        );
+        wait_for_result(&mut handle, Duration::from_secs(30)).expect("search never finished");
+        assert_eq!(
+            receiver.try_iter().collect::<Vec<_>>(),
+            vec![Some(1), Some(2), Some(3), None]
+        )
+    }
+
+    #[test]
+    fn test_stop_ends_the_search_promptly() {
+        let searcher = Searcher::new(BoardKind::Traditional.shared_move_tables());
+        let mut position = Position::new_traditional();
+        // Far too deep to ever finish on its own
+        let mut handle = SearchHandle::spawn(searcher, position.clone(), 30, None);
+        thread::sleep(Duration::from_millis(50));
+        handle.stop();
+        let result = wait_for_result(&mut handle, Duration::from_secs(2)).expect("stop was ignored");
+        let best_move = result.best_move.unwrap();
+        let searcher = handle.into_searcher();
+        assert!(searcher.movegen.get_legal_moves(&mut position).contains(&best_move))
+    }
+}
@@ -1,50 +1,238 @@
-use crate::{chess_move::Move, evaluator::{Evaluator, CHECKMATED_SCORE}, move_generator::MoveTables, position::Position, transposition_table::{TranspositionTable, Flag}};
+use std::sync::{atomic::{AtomicBool, Ordering}, Arc};
+use std::time::{Duration, Instant};
 
-#[derive(Debug)]
+use crate::{chess_move::Move, evaluator::{EvalParams, Evaluator, CHECKMATED_SCORE}, move_generator::MoveTables, piece_set::Color, position::{Position, FIFTY_MOVE_PLIES}, transposition_table::{TranspositionTable, Flag}};
+
+// Time management: assumed moves left when the clock doesn't say, and the most of the clock one move may use
+const DEFAULT_MOVES_TO_GO: u64 = 30;
+const MAX_CLOCK_FRACTION: u64 = 2;
+pub const MAX_TIMED_DEPTH: u8 = 64;
+
+// Bounds the one-reply extensions along a single line, in case both sides keep having forced moves
+const MAX_EXTENSIONS: u8 = 8;
+
+// How many nodes pass between checks of the stop signal
+const STOP_CHECK_INTERVAL: usize = 1024;
+
+// A mated score is CHECKMATED_SCORE plus the ply it happens at, so anything this close to it is a mate
+const MAX_MATE_PLY: i32 = 256;
+
+#[derive(Debug, Clone, Copy)]
+pub struct LateMoveReductions {
+    pub full_depth_moves: usize, // The first moves (best ordered) are always searched at full depth
+    pub min_depth: u8, // Shallower nodes are never reduced
+    pub reduction: u8 // Plies removed from a late move's search
+}
+
+impl LateMoveReductions {
+    pub fn new(full_depth_moves: usize, min_depth: u8, reduction: u8) -> Self {
+        LateMoveReductions { full_depth_moves, min_depth, reduction }
+    }
+
+    fn reduced_depth(&self, move_idx: usize, depth: u8, is_tactical: bool) -> Option<u8> {
+        // Captures, checks and promotions are never reduced
+        if is_tactical || move_idx < self.full_depth_moves || depth < self.min_depth {
+            return None
+        }
+        Some((depth - 1).saturating_sub(self.reduction))
+    }
+}
+
+#[derive(Debug, Clone)]
 pub struct SearchResult {
     pub best_move: Option<Move>,
-    pub best_score: i32
+    pub best_score: i32,
+    pub mate_in: Option<i32>, // Moves, positive if the side to move mates and negative if it gets mated
+    pub stats: SearchStats
+}
+
+#[derive(Debug, PartialEq)]
+pub enum SearchError {
+    NoRootMoves,
+    IllegalRootMove(Move)
+}
+
+// Totals for one top-level search, counted from zero each time
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct SearchStats {
+    pub nodes: usize,
+    pub depth_reached: u8, // Deepest depth that finished
+    pub time_elapsed: Duration,
+    pub nps: u64
+}
+
+impl SearchStats {
+    pub fn new(nodes: usize, depth_reached: u8, time_elapsed: Duration) -> Self {
+        let nps = Searcher::nodes_per_second(nodes, time_elapsed);
+        return SearchStats { nodes, depth_reached, time_elapsed, nps }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Score {
+    Cp(i32), // Centipawns for the side to move
+    Mate(i32) // Moves until mate, negative if the side to move is getting mated
+}
+
+impl Score {
+    pub fn from_search(score: i32) -> Self {
+        // Mate scores are CHECKMATED_SCORE pulled in by the ply (from the root) the mate happens at
+        let mate_bound = -CHECKMATED_SCORE as i32;
+        if score.abs() <= mate_bound - MAX_MATE_PLY {
+            return Score::Cp(score)
+        }
+        let plies = mate_bound - score.abs();
+        let moves = (plies + 1) / 2;
+        match score > 0 {
+            true => Score::Mate(moves),
+            false => Score::Mate(-moves)
+        }
+    }
+
+    pub fn to_white_perspective(&self, side_to_move: Color) -> Self {
+        // Kept as a Score rather than a bare i32 so a mate isn't mistaken for centipawns
+        return match (side_to_move, *self) {
+            (Color::White, _) => *self,
+            (Color::Black, Score::Cp(centipawns)) => Score::Cp(-centipawns),
+            (Color::Black, Score::Mate(moves)) => Score::Mate(-moves)
+        }
+    }
+}
+
+fn mate_score_to_tt(score: i32, ply: u8) -> i32 {
+    // Stored relative to the node rather than the root, so the entry is right at any ply
+    let mate_bound = -CHECKMATED_SCORE as i32;
+    if score > mate_bound - MAX_MATE_PLY {
+        return score.saturating_add(ply as i32)
+    } else if score < MAX_MATE_PLY - mate_bound {
+        return score.saturating_sub(ply as i32)
+    }
+    score
+}
+
+fn mate_score_from_tt(score: i32, ply: u8) -> i32 {
+    let mate_bound = -CHECKMATED_SCORE as i32;
+    if score > mate_bound - MAX_MATE_PLY {
+        return score.saturating_sub(ply as i32)
+    } else if score < MAX_MATE_PLY - mate_bound {
+        return score.saturating_add(ply as i32)
+    }
+    score
+}
+
+// Reported after each completed depth, in the shape of a UCI info line
+#[derive(Debug, Clone)]
+pub struct SearchInfo {
+    pub depth: u8,
+    pub seldepth: u8, // Deepest ply reached, counting extensions
+    pub score: Score,
+    pub nodes: usize,
+    pub nps: u64,
+    pub time_ms: u64,
+    pub pv: Vec<Move>,
+    pub hashfull: u16
 }
 
 pub struct Searcher {
     transposition_table: TranspositionTable,
-    evaluator: Evaluator,
-    pub movegen: MoveTables,
+    pub evaluator: Evaluator,
+    pub movegen: Arc<MoveTables>, // Shared, see BoardKind::shared_move_tables
+    pub late_move_reductions: Option<LateMoveReductions>,
+    pub material_only: bool,
+    pub use_tt: bool, // Off bypasses the transposition table, to tell table bugs apart from search bugs
+    pub contempt: i32, // How much worse than equal the root player considers a draw
+    pub stop_signal: Option<Arc<AtomicBool>>, // Set from another thread to end the search early
+    stopped: bool,
+    root_player: Color,
     nodes_searched: usize,
+    extensions: u8,
+    max_extensions: u8,
+    ply: u8 // Distance from the root of the current search
 }
 
 impl Searcher {
-    pub fn new(movegen: MoveTables) -> Self {
+    pub fn new(movegen: Arc<MoveTables>) -> Self {
         Searcher {
             transposition_table: TranspositionTable::new(),
-            evaluator: Evaluator::new(&movegen),
+            evaluator: Evaluator::new(movegen.clone(), EvalParams::default()),
             movegen,
-            nodes_searched: 0
+            late_move_reductions: Some(LateMoveReductions::new(4, 3, 1)),
+            material_only: false,
+            use_tt: true,
+            contempt: 0,
+            stop_signal: None,
+            stopped: false,
+            root_player: Color::White,
+            nodes_searched: 0,
+            extensions: 0,
+            max_extensions: 0,
+            ply: 0
         }
     }
 
     pub fn alpha_beta(&mut self, position: &mut Position, mut alpha: i32, beta: i32, depth: u8) -> i32 {
-        
+        self.nodes_searched += 1;
+
+        // Once stopped every score is meaningless, callers throw away the unfinished depth
+        if self.nodes_searched % STOP_CHECK_INTERVAL == 0 {
+            if let Some(stop_signal) = &self.stop_signal {
+                self.stopped |= stop_signal.load(Ordering::Relaxed);
+            }
+        }
+        if self.stopped {
+            return 0
+        }
+
+        // A repeat or a claimable fifty-move draw below the root is scored as the draw either side could force
+        if self.ply > 0 && (position.repetition_count() >= 2 || position.record.fifty_move_counter >= FIFTY_MOVE_PLIES) {
+            return self.draw_score(position)
+        }
+
+        // Without mating material on either side nothing can be forced, but a side in check could still be mated
+        if !position.has_mating_material(&Color::White) && !position.has_mating_material(&Color::Black)
+            && !position.is_in_check(&self.movegen, &position.active_player) {
+            return self.draw_score(position)
+        }
+
         if depth == 0 {
-            return self.evaluator.static_evaluate(position) as i32
+            return match self.material_only {
+                true => self.evaluator.material_only(position) as i32,
+                false => self.evaluator.cached_evaluate(position) as i32
+            }
         }
 
         // --- TRANSPOSITION TABLE PROBE (Optional but highly recommended) ---
         let key = position.get_zobrist();
-        if let Some(tt_score) = self.transposition_table.retrieve(key, depth, alpha, beta) {
-            return tt_score;
+        let tt_alpha = mate_score_to_tt(alpha, self.ply);
+        let tt_beta = mate_score_to_tt(beta, self.ply);
+        if self.use_tt {
+            if let Some(tt_score) = self.transposition_table.retrieve(key, depth, tt_alpha, tt_beta) {
+                return mate_score_from_tt(tt_score, self.ply);
+            }
         }
 
-        // --- BASE CASE 2: Check for Game Over (Mate/Stalemate) ---
-        let legal_moves = self.movegen.get_legal_moves(position);
-        if legal_moves.is_empty() {
-            return if position.is_checkmate(&self.movegen) {
-                // Return a mate score adjusted by depth (shallower mate is better)
-                -CHECKMATED_SCORE as i32 + depth as i32
-            } else {
-                // Stalemate
-                0 
-            };
+        // --- ONE-REPLY EXTENSION: A forced move is searched without reducing the depth ---
+        if self.extensions < MAX_EXTENSIONS {
+            if let Some(forced_move) = self.movegen.has_single_legal_move(position) {
+                self.extensions += 1;
+                self.max_extensions = self.max_extensions.max(self.extensions);
+                position.make_legal_move(&forced_move);
+                self.ply += 1;
+                let score = -self.alpha_beta(position, -beta, -alpha, depth);
+                self.ply -= 1;
+                position.unmake_legal_move(&forced_move);
+                self.extensions -= 1;
+                // Stored like any other node, so the principal variation can follow the line through it
+                if self.use_tt && !self.stopped {
+                    let flag = match (score <= alpha, score >= beta) {
+                        (true, _) => Flag::UpperBound,
+                        (_, true) => Flag::LowerBound,
+                        _ => Flag::Exact
+                    };
+                    self.transposition_table.store(key, mate_score_to_tt(score, self.ply), depth, flag, Some(forced_move));
+                }
+                return score
+            }
         }
 
         // --- ITERATION AND RECURSION ---
@@ -54,11 +242,39 @@ impl Searcher {
 
         // 1. Move Ordering/Generation
         // (Move ordering is critical! Sort moves by importance: TT-move, captures, checks, etc.)
-        // let ordered_moves = self.order_moves(position, legal_moves);
+        // Captures are searched first so late move reductions mostly apply to quiet moves
+        // Moves that need a make to judge legality are judged after the make the recursion needs anyway
+        let moving_player = position.active_player;
+        let mut ordered_moves = self.movegen.get_unverified_moves(position);
+        ordered_moves.sort_by_key(|(chess_move, _)| !position.is_capture(chess_move));
 
-        for current_move in self.movegen.get_legal_moves(position) {
+        let mut move_idx = 0;
+        for (current_move, needs_verifying) in ordered_moves.into_iter() {
+            let is_capture = position.is_capture(&current_move);
             position.make_legal_move(&current_move);
-            let score = -self.alpha_beta(position, -beta, -alpha, depth - 1);
+            if needs_verifying && position.is_in_check(&self.movegen, &moving_player) {
+                position.unmake_legal_move(&current_move);
+                continue
+            }
+            self.ply += 1;
+            let gives_check = position.is_in_check(&self.movegen, &position.active_player);
+            let is_tactical = is_capture || gives_check || current_move.promotion.is_some();
+
+            let reduced_depth = self.late_move_reductions
+                .and_then(|lmr| lmr.reduced_depth(move_idx, depth, is_tactical));
+            move_idx += 1;
+            let score = match reduced_depth {
+                Some(reduced_depth) => {
+                    // Null window search at reduced depth, only re-searched fully if it might beat alpha
+                    let reduced_score = -self.alpha_beta(position, -alpha - 1, -alpha, reduced_depth);
+                    match reduced_score > alpha {
+                        true => -self.alpha_beta(position, -beta, -alpha, depth - 1),
+                        false => reduced_score
+                    }
+                },
+                None => -self.alpha_beta(position, -beta, -alpha, depth - 1)
+            };
+            self.ply -= 1;
             position.unmake_legal_move(&current_move);
 
             if score > best_score {
@@ -76,6 +292,21 @@ impl Searcher {
                 break; // PRUNE!
             }
         }
+
+        // Nothing from an interrupted node belongs in the table
+        if self.stopped {
+            return 0
+        }
+
+        // --- BASE CASE 2: Check for Game Over (Mate/Stalemate) ---
+        if move_idx == 0 {
+            return match position.is_in_check(&self.movegen, &moving_player) {
+                // Return a mate score adjusted by ply (shallower mate is better)
+                true => CHECKMATED_SCORE as i32 + self.ply as i32,
+                // Stalemate
+                false => self.draw_score(position)
+            };
+        }
         
         // --- TRANSPOSITION TABLE STORE ---
         if best_score >= beta {
@@ -86,17 +317,65 @@ impl Searcher {
             flag = Flag::UpperBound; // best_score <= alpha (the upper bound on the true score)
         }
 
-        self.transposition_table.store(key, best_score, depth, flag, best_move);
+        if self.use_tt {
+            self.transposition_table.store(key, mate_score_to_tt(best_score, self.ply), depth, flag, best_move);
+        }
 
         return best_score;
     }
 
+    fn draw_score(&self, position: &Position) -> i32 {
+        return match position.active_player == self.root_player {
+            true => -self.contempt,
+            false => self.contempt
+        }
+    }
+
     pub fn get_best_move(&mut self, position: &mut Position, max_depth: u8) -> SearchResult {
+        return self.search_fixed_depth(position, max_depth, None)
+    }
+
+    pub fn get_best_move_restricted(&mut self, position: &mut Position, root_moves: &[Move], max_depth: u8) -> Result<SearchResult, SearchError> {
+        // Only root_moves are tried at the root, so each one has to be legal here or the result would look like a stalemate
+        if root_moves.is_empty() {
+            return Err(SearchError::NoRootMoves)
+        }
         let legal_moves = self.movegen.get_legal_moves(position);
+        if let Some(illegal) = root_moves.iter().find(|root_move| !legal_moves.contains(root_move)) {
+            return Err(SearchError::IllegalRootMove(illegal.clone()))
+        }
+        return Ok(self.search_fixed_depth(position, max_depth, Some(root_moves)))
+    }
+
+    fn search_fixed_depth(&mut self, position: &mut Position, max_depth: u8, root_moves: Option<&[Move]>) -> SearchResult {
+        let start = Instant::now();
+        self.nodes_searched = 0;
+        let mut result = self.search_root(position, max_depth, root_moves);
+        let depth_reached = match self.stopped {
+            true => 0,
+            false => max_depth
+        };
+        result.stats = SearchStats::new(self.nodes_searched, depth_reached, start.elapsed());
+        return result
+    }
+
+    pub fn nodes_per_second(nodes: usize, time_elapsed: Duration) -> u64 {
+        // Under a microsecond counts as one, so a tiny search doesn't divide by zero
+        return (nodes as u128 * 1_000_000 / time_elapsed.as_micros().max(1)) as u64
+    }
+
+    fn search_root(&mut self, position: &mut Position, max_depth: u8, root_moves: Option<&[Move]>) -> SearchResult {
+        // One fixed-depth search, the node count carries on from wherever the caller left it
+        self.root_player = position.active_player;
+        self.stopped = false;
+        let mut legal_moves = self.movegen.get_legal_moves(position);
+        if let Some(root_moves) = root_moves {
+            legal_moves.retain(|legal_move| root_moves.contains(legal_move));
+        }
         
-        // Handle no moves case (mate or stalemate)
+        // Handle no moves case (mate, stalemate or no legal move to search)
         if legal_moves.is_empty() {
-            return SearchResult { best_move: None, best_score: 0 };
+            return SearchResult { best_move: None, best_score: 0, mate_in: None, stats: SearchStats::default() };
         }
 
         let mut best_score = i32::MIN;
@@ -108,15 +387,19 @@ impl Searcher {
         let beta = i32::MAX; 
 
         // 2. Iterate through all root moves
-        for current_move in legal_moves {
+        for current_move in legal_moves.iter().cloned() {
             // 3. Make the move on the board
             position.make_legal_move(&current_move);
+            self.ply = 1;
             // 4. Call the Negamax Alpha-Beta function
             // We flip alpha and beta and negate the result as required by Negamax.
-            println!("{:?}", max_depth);
             let score = -self.alpha_beta(position, -beta, -alpha, max_depth - 1);
             // 5. Unmake the move
+            self.ply = 0;
             position.unmake_legal_move(&current_move);
+            if self.stopped {
+                break
+            }
 
             // 6. Update the Best Move and Score
             if score > best_score {
@@ -127,10 +410,584 @@ impl Searcher {
             }
         }
         
+        // A search stopped before any root move finished still has to return some move
+        if best_move.is_none() {
+            best_move = legal_moves.first().cloned();
+            best_score = 0;
+        }
+
         // Return the final result
+        let mate_in = match Score::from_search(best_score) {
+            Score::Mate(moves) => Some(moves),
+            Score::Cp(_) => None
+        };
         SearchResult {
             best_move,
-            best_score
+            best_score,
+            mate_in,
+            stats: SearchStats::default() // Filled in by the top-level search
+        }
+    }
+
+    pub fn allocate_time(remaining_ms: u64, increment_ms: u64, moves_to_go: u32) -> u64 {
+        // An even share of the clock plus most of the increment, moves_to_go of 0 means unknown
+        let moves_to_go = match moves_to_go {
+            0 => DEFAULT_MOVES_TO_GO,
+            n => n as u64
+        };
+        let budget = remaining_ms / moves_to_go + increment_ms * 3 / 4;
+        budget.min(remaining_ms / MAX_CLOCK_FRACTION)
+    }
+
+    pub fn search_for_time(&mut self, position: &mut Position, time_ms: u64) -> SearchResult {
+        self.search_with_info(position, MAX_TIMED_DEPTH, Some(time_ms), &mut |_info| {})
+    }
+
+    pub fn search_with_info(&mut self, position: &mut Position, max_depth: u8, time_ms: Option<u64>, on_info: &mut dyn FnMut(&SearchInfo)) -> SearchResult {
+        // Iterative deepening, a depth is only started if it is likely to finish inside the budget
+        let start = Instant::now();
+        self.nodes_searched = 0;
+        let mut result = SearchResult { best_move: None, best_score: 0, mate_in: None, stats: SearchStats::default() };
+        let mut depth_reached = 0;
+        for depth in 1..=max_depth {
+            let elapsed_ms = start.elapsed().as_millis() as u64;
+            // Each depth takes several times longer than the last, so stop once half the budget is gone
+            if let Some(time_ms) = time_ms {
+                if depth > 1 && elapsed_ms * 2 >= time_ms {
+                    break
+                }
+            }
+            self.max_extensions = 0;
+            let depth_result = self.search_root(position, depth, None);
+            // An interrupted depth is only better than nothing
+            if self.stopped && result.best_move.is_some() {
+                break
+            }
+            result = depth_result;
+            if self.stopped {
+                break
+            }
+            depth_reached = depth;
+            let best_move = match &result.best_move {
+                Some(best_move) => best_move.clone(),
+                None => break // Nothing to search
+            };
+            let elapsed = start.elapsed();
+            on_info(&SearchInfo {
+                depth,
+                seldepth: depth + self.max_extensions,
+                score: Score::from_search(result.best_score),
+                nodes: self.nodes_searched,
+                nps: Self::nodes_per_second(self.nodes_searched, elapsed),
+                time_ms: elapsed.as_millis() as u64,
+                pv: self.principal_variation(position, best_move, depth + self.max_extensions),
+                hashfull: self.transposition_table.hashfull()
+            });
         }
+        result.stats = SearchStats::new(self.nodes_searched, depth_reached, start.elapsed());
+        result
+    }
+
+    fn principal_variation(&mut self, position: &mut Position, first_move: Move, max_length: u8) -> Vec<Move> {
+        // Follows the best moves stored in the transposition table, stopping at anything that isn't legal here
+        let mut pv = vec![first_move];
+        position.make_legal_move(&pv[0]);
+        while pv.len() < max_length as usize {
+            let next_move = match self.transposition_table.best_move(position.get_zobrist()) {
+                Some(packed_move) => packed_move.unpack(&self.movegen),
+                None => break
+            };
+            if !self.movegen.get_legal_moves(position).contains(&next_move) {
+                break
+            }
+            position.make_legal_move(&next_move);
+            pv.push(next_move);
+        }
+        for chess_move in pv.iter().rev() {
+            position.unmake_legal_move(chess_move);
+        }
+        pv
+    }
+
+    pub fn find_mate(&mut self, position: &mut Position, max_moves: u8) -> Option<Vec<Move>> {
+        // Returns the full mating line (attacking moves and defending replies), shortest mate first
+        for num_moves in 1..=max_moves {
+            if let Some(mating_line) = self.mate_search(position, num_moves) {
+                return Some(mating_line)
+            }
+        }
+        None
+    }
+
+    fn mate_search(&mut self, position: &mut Position, moves_left: u8) -> Option<Vec<Move>> {
+        // Attacker only considers checking moves, defender considers every reply
+        for attacking_move in self.movegen.get_legal_moves(position) {
+            position.make_legal_move(&attacking_move);
+            let mut mating_line = None;
+            if position.is_in_check(&self.movegen, &position.active_player) {
+                let replies = self.movegen.get_legal_moves(position);
+                if replies.is_empty() {
+                    mating_line = Some(vec![attacking_move.clone()]);
+                } else if moves_left > 1 {
+                    mating_line = self.refute_all_replies(position, replies, moves_left - 1);
+                    if let Some(line) = &mut mating_line {
+                        line.insert(0, attacking_move.clone());
+                    }
+                }
+            }
+            position.unmake_legal_move(&attacking_move);
+            if mating_line.is_some() {
+                return mating_line
+            }
+        }
+        None
+    }
+
+    fn refute_all_replies(&mut self, position: &mut Position, replies: Vec<Move>, moves_left: u8) -> Option<Vec<Move>> {
+        // Every reply must still lose to a forced mate, the defender picks the longest resistance
+        let mut longest_line: Option<Vec<Move>> = None;
+        for reply in replies {
+            position.make_legal_move(&reply);
+            let continuation = self.mate_search(position, moves_left);
+            position.unmake_legal_move(&reply);
+            match continuation {
+                None => return None,
+                Some(mut line) => {
+                    if longest_line.as_ref().map_or(true, |longest| line.len() + 1 > longest.len()) {
+                        line.insert(0, reply);
+                        longest_line = Some(line);
+                    }
+                }
+            }
+        }
+        longest_line
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::graph_boards::graph_board::TileIndex;
+    use crate::bit_board::BitBoard;
+    use crate::graph_boards::board_kind::BoardKind;
+    use crate::notation::coordinate_to_move;
+    use crate::piece_set::PieceType;
+
+    fn test_searcher() -> Searcher {
+        Searcher::new(BoardKind::Traditional.shared_move_tables())
+    }
+
+    fn from_moves(coordinates: &[&str], move_tables: &MoveTables) -> Position {
+        let mut position = Position::new_traditional();
+        for coordinate in coordinates {
+            let chess_move = coordinate_to_move(&mut position, coordinate, move_tables).unwrap();
+            position.make_legal_move(&chess_move);
+        }
+        return position
+    }
+
+    fn minimax(searcher: &Searcher, position: &mut Position, depth: u8, ply: i32) -> i32 {
+        // Reference search with no pruning, table, reductions or extensions
+        if depth == 0 {
+            return searcher.evaluator.static_evaluate(position) as i32
+        }
+        let legal_moves = searcher.movegen.get_legal_moves(position);
+        if legal_moves.is_empty() {
+            return match position.is_in_check(&searcher.movegen, &position.active_player) {
+                true => CHECKMATED_SCORE as i32 + ply,
+                false => 0
+            }
+        }
+        let mut best_score = i32::MIN + 1;
+        for legal_move in legal_moves {
+            position.make_legal_move(&legal_move);
+            best_score = best_score.max(-minimax(searcher, position, depth - 1, ply + 1));
+            position.unmake_legal_move(&legal_move);
+        }
+        return best_score
+    }
+
+    fn minimax_best_move(searcher: &Searcher, position: &mut Position, depth: u8) -> Move {
+        let mut best = (i32::MIN, None);
+        for legal_move in searcher.movegen.get_legal_moves(position) {
+            position.make_legal_move(&legal_move);
+            let score = -minimax(searcher, position, depth - 1, 1);
+            position.unmake_legal_move(&legal_move);
+            if score > best.0 {
+                best = (score, Some(legal_move));
+            }
+        }
+        return best.1.unwrap()
+    }
+
+    #[test]
+    fn test_search_without_tt_matches_minimax() {
+        let mut searcher = test_searcher();
+        searcher.use_tt = false;
+        searcher.late_move_reductions = None;
+        // Nc7+ forks the king on e8 and the rook on a8
+        let mut position = Position::from_string("4K28N22r3k3 w -".to_string());
+        let result = searcher.get_best_move(&mut position, 3);
+        assert_eq!(
+            result.best_move,
+            Some(minimax_best_move(&searcher, &mut position, 3))
+        );
+        assert_eq!(
+            result.best_move,
+            Some(Move::new(TileIndex::new(33), TileIndex::new(50), None, None))
+        );
+        assert_eq!(searcher.transposition_table.hashfull(), 0)
+    }
+
+    #[test]
+    fn test_underpromotion_to_mate() {
+        let mut searcher = test_searcher();
+        // b8=N mates the king on a6, while b8=Q leaves it with no moves and no check
+        let mut position = Position::from_string("40kBK6P14 w -".to_string());
+        let legal_moves = searcher.movegen.get_legal_moves(&mut position);
+        for piece_type in [PieceType::Queen, PieceType::Rook, PieceType::Bishop, PieceType::Knight] {
+            assert!(legal_moves.contains(&Move::new(TileIndex::new(49), TileIndex::new(57), Some(piece_type), None)));
+        }
+        let queen_promotion = Move::new(TileIndex::new(49), TileIndex::new(57), Some(PieceType::Queen), None);
+        position.make_legal_move(&queen_promotion);
+        assert!(position.is_stalemate(&searcher.movegen));
+        position.unmake_legal_move(&queen_promotion);
+
+        let result = searcher.get_best_move(&mut position, 2);
+        assert_eq!(
+            result.best_move,
+            Some(Move::new(TileIndex::new(49), TileIndex::new(57), Some(PieceType::Knight), None))
+        );
+        assert_eq!(
+            result.mate_in,
+            Some(1)
+        )
+    }
+
+    #[test]
+    fn test_find_mate_in_two() {
+        let mut searcher = test_searcher();
+        // Back rank mate: 1. Re8+ Rxe8 2. Rxe8#
+        let mut position = Position::from_string("4R2K4R40ppp2r3k w -".to_string());
+        assert_eq!(
+            searcher.find_mate(&mut position, 1),
+            None
+        );
+        assert_eq!(
+            searcher.find_mate(&mut position, 2),
+            Some(vec![
+                Move::new(TileIndex::new(12), TileIndex::new(60), None, None),
+                Move::new(TileIndex::new(58), TileIndex::new(60), None, None),
+                Move::new(TileIndex::new(4), TileIndex::new(60), None, None)
+            ])
+        );
+    }
+
+    #[test]
+    fn test_mate_in_two_is_reported() {
+        let mut searcher = test_searcher();
+        let result = searcher.get_best_move(&mut Position::from_string("4R2K4R40ppp2r3k w -".to_string()), 4);
+        assert_eq!(
+            result.mate_in,
+            Some(2)
+        );
+        assert_eq!(
+            test_searcher().get_best_move(&mut Position::new_traditional(), 2).mate_in,
+            None
+        )
+    }
+
+    #[test]
+    fn test_late_move_reductions_reduce_nodes() {
+        // Nc7+ forks the king on e8 and the rook on a8
+        let fork = Move::new(TileIndex::new(33), TileIndex::new(50), None, None);
+        let mut full_searcher = test_searcher();
+        full_searcher.late_move_reductions = None;
+        let full_result = full_searcher.get_best_move(&mut Position::from_string("4K28N22r3k3 w -".to_string()), 6);
+
+        let mut reduced_searcher = test_searcher();
+        let reduced_result = reduced_searcher.get_best_move(&mut Position::from_string("4K28N22r3k3 w -".to_string()), 6);
+
+        assert_eq!(
+            full_result.best_move,
+            Some(fork.clone())
+        );
+        assert_eq!(
+            reduced_result.best_move,
+            Some(fork)
+        );
+        assert!(reduced_searcher.nodes_searched < full_searcher.nodes_searched)
+    }
+
+    #[test]
+    fn test_bare_kings_are_drawn_without_search() {
+        let mut searcher = test_searcher();
+        let mut position = Position::from_string("4K55k w -".to_string());
+        assert_eq!(
+            searcher.alpha_beta(&mut position, -i32::MAX, i32::MAX, 1),
+            0
+        );
+        assert_eq!( // No moves were generated, so no child nodes were visited
+            searcher.nodes_searched,
+            1
+        );
+        searcher.contempt = 20;
+        searcher.root_player = Color::Black;
+        assert_eq!(
+            searcher.alpha_beta(&mut position, -i32::MAX, i32::MAX, 1),
+            20
+        )
+    }
+
+    #[test]
+    fn test_pruning_node_count() {
+        // Raise the threshold only on purpose, e.g. after move ordering gets better
+        const NODE_THRESHOLD: usize = 10_000; // About 3700 when last set
+        const DEPTH: u8 = 4;
+        let mut searcher = test_searcher();
+        let italian = ["e2e4", "e7e5", "g1f3", "b8c6", "f1c4", "g8f6"];
+        let mut position = from_moves(&italian, &searcher.movegen);
+        searcher.get_best_move(&mut position, DEPTH);
+        let mut negamax_nodes = 1;
+        for depth in 1..=DEPTH {
+            negamax_nodes += searcher.movegen.perft(&mut from_moves(&italian, &searcher.movegen), depth) as usize;
+        }
+        assert!(searcher.nodes_searched < NODE_THRESHOLD);
+        assert!(searcher.nodes_searched * 50 < negamax_nodes) // Plain negamax visits every node perft counts
+    }
+
+    #[test]
+    fn test_allocate_time() {
+        // 10 seconds left with 40 moves to go leaves a quarter second per move
+        let allocated = Searcher::allocate_time(10_000, 0, 40);
+        assert_eq!(
+            allocated,
+            250
+        );
+        assert!(allocated * 20 < 10_000);
+        // Most of the increment is spent, the clock never drops below half
+        assert_eq!(
+            Searcher::allocate_time(10_000, 1_000, 0),
+            10_000 / 30 + 750
+        );
+        assert_eq!(
+            Searcher::allocate_time(1_000, 2_000, 1),
+            500
+        )
+    }
+
+    #[test]
+    fn test_search_for_time_returns_a_move() {
+        let mut searcher = test_searcher();
+        let mut position = Position::new_traditional();
+        let start = Instant::now();
+        let result = searcher.search_for_time(&mut position, 50);
+        assert!(result.best_move.is_some());
+        assert!(start.elapsed().as_millis() < 5_000)
+    }
+
+    #[test]
+    fn test_search_info_scores() {
+        let mut searcher = test_searcher();
+        // Back rank mate: Ra8#
+        let mut position = Position::from_string("R1K51pp7k w -".to_string());
+        let mut infos = vec![];
+        searcher.search_with_info(&mut position, 2, None, &mut |info| infos.push(info.clone()));
+        let last_info = infos.last().unwrap();
+        assert_eq!(
+            last_info.score,
+            Score::Mate(1)
+        );
+        assert_eq!(
+            last_info.pv[0],
+            Move::new(TileIndex::new(0), TileIndex::new(56), None, None)
+        );
+
+        let mut infos = vec![];
+        searcher.search_with_info(&mut Position::new_traditional(), 3, None, &mut |info| infos.push(info.clone()));
+        assert_eq!(
+            infos.iter().map(|info| info.depth).collect::<Vec<u8>>(),
+            vec![1, 2, 3]
+        );
+        for info in &infos {
+            assert!(matches!(info.score, Score::Cp(_)));
+            // Worked out from the exact elapsed time, which is never under the rounded down time_ms
+            assert!(info.nps > 0);
+            assert!(info.nps <= Searcher::nodes_per_second(info.nodes, Duration::from_millis(info.time_ms)));
+            assert!(!info.pv.is_empty() && info.pv.len() <= info.depth as usize);
+        }
+    }
+
+    #[test]
+    fn test_score_to_white_perspective() {
+        assert_eq!(
+            Score::Cp(100).to_white_perspective(Color::Black),
+            Score::Cp(-100)
+        );
+        assert_eq!(
+            Score::Cp(100).to_white_perspective(Color::White),
+            Score::Cp(100)
+        );
+        assert_eq!(
+            Score::Mate(-2).to_white_perspective(Color::Black),
+            Score::Mate(2)
+        )
+    }
+
+    #[test]
+    fn test_score_from_search() {
+        assert_eq!(Score::from_search(35), Score::Cp(35));
+        // The opponent is mated one ply below the root
+        assert_eq!(Score::from_search(-CHECKMATED_SCORE as i32 - 1), Score::Mate(1));
+        // Mated four plies below the root
+        assert_eq!(Score::from_search(CHECKMATED_SCORE as i32 + 4), Score::Mate(-2))
+    }
+
+    #[test]
+    fn test_searchers_share_move_tables() {
+        let first = Searcher::new(BoardKind::Traditional.shared_move_tables());
+        let second = Searcher::new(BoardKind::Traditional.shared_move_tables());
+        assert!(Arc::ptr_eq(&first.movegen, &second.movegen));
+        let occupied = BitBoard::from_ints(vec![12, 19, 42, 51]);
+        for piece_type in [PieceType::King, PieceType::Queen, PieceType::Rook, PieceType::Bishop, PieceType::Knight] {
+            for source_tile in 0..64 {
+                assert_eq!(
+                    first.movegen.query_piece(&piece_type, TileIndex::new(source_tile), occupied),
+                    second.movegen.query_piece(&piece_type, TileIndex::new(source_tile), occupied)
+                )
+            }
+        }
+        assert!(!Arc::ptr_eq(&first.movegen, &BoardKind::Hexagonal.shared_move_tables()))
+    }
+
+    #[test]
+    fn test_lone_knight_is_a_draw() {
+        let mut searcher = test_searcher();
+        let mut knight_vs_king = Position::from_string("4K22N32k3 w -".to_string());
+        assert!(searcher.evaluator.static_evaluate(&knight_vs_king) > 300);
+        assert_eq!(
+            searcher.get_best_move(&mut knight_vs_king, 3).best_score,
+            0
+        );
+        // A pawn can still promote, so it isn't cut short
+        let mut pawn_vs_king = Position::from_string("4K22N3P28k3 w -".to_string());
+        assert!(searcher.get_best_move(&mut pawn_vs_king, 3).best_score > 300);
+        // Neither side can force mate, but Bg7 is still mate with the knight in the way
+        let mut helpmate = Position::from_string("47B5K1n7k w -".to_string());
+        let result = searcher.get_best_move(&mut helpmate, 2);
+        assert_eq!(result.best_move, Some(Move::new(TileIndex::new(47), TileIndex::new(54), None, None)));
+        assert_eq!(result.mate_in, Some(1))
+    }
+
+    #[test]
+    fn test_contempt_avoids_repetition() {
+        let mut searcher = test_searcher();
+        let mut position = from_moves(&["g1f3", "g8f6", "f3g1", "f6g8", "g1f3", "g8f6"], &searcher.movegen);
+        let repeating_move = coordinate_to_move(&mut position, "f3g1", &searcher.movegen).unwrap();
+
+        // Scored from the root player's side, one ply down like the root loop does
+        let repeat_score = |searcher: &mut Searcher, position: &mut Position| {
+            searcher.root_player = position.active_player;
+            position.make_legal_move(&repeating_move);
+            searcher.ply = 1;
+            let score = -searcher.alpha_beta(position, -i32::MAX, i32::MAX, 2);
+            searcher.ply = 0;
+            position.unmake_legal_move(&repeating_move);
+            return score
+        };
+
+        // Without contempt repeating is exactly as good as an equal position
+        assert_eq!(repeat_score(&mut searcher, &mut position), 0);
+        searcher.contempt = 50;
+        assert_eq!(repeat_score(&mut searcher, &mut position), -50);
+        let result = searcher.get_best_move(&mut position, 3);
+        assert_ne!(result.best_move, Some(repeating_move));
+        assert!(result.best_score > -50)
+    }
+
+    #[test]
+    fn test_winning_side_avoids_threefold_repetition() {
+        // A rook up, with the knight shuffle about to repeat the position a third time
+        let mut searcher = test_searcher();
+        let mut position = Position::from_string("R3K16N38k3 b -".to_string());
+        for coordinate in ["e8d8", "f3g1", "d8e8", "g1f3", "e8d8", "f3g1", "d8e8"] {
+            let next_move = coordinate_to_move(&mut position, coordinate, &searcher.movegen).unwrap();
+            position.make_legal_move(&next_move);
+        }
+        let repeating_move = coordinate_to_move(&mut position, "g1f3", &searcher.movegen).unwrap();
+
+        // Repeating scores as a draw, so the winning side plays on instead
+        let result = searcher.get_best_move(&mut position, 3);
+        assert!(result.best_score > 0);
+        assert_ne!(result.best_move, Some(repeating_move.clone()));
+        assert_eq!(
+            searcher.get_best_move_restricted(&mut position, &[repeating_move], 3).unwrap().best_score,
+            0
+        )
+    }
+
+    #[test]
+    fn test_restricted_root_moves() {
+        // Rxe8 wins a rook, restricted to a quiet king move the search has to settle for less
+        let mut searcher = test_searcher();
+        let mut position = Position::from_string("4R2K52r2k w -".to_string());
+        let capture = coordinate_to_move(&mut position, "e1e8", &searcher.movegen).unwrap();
+        let king_move = coordinate_to_move(&mut position, "h1g1", &searcher.movegen).unwrap();
+        let unrestricted = searcher.get_best_move(&mut position, 3);
+        assert_eq!(unrestricted.best_move, Some(capture.clone()));
+
+        let restricted = searcher.get_best_move_restricted(&mut position, &[king_move.clone()], 3).unwrap();
+        assert_eq!(restricted.best_move, Some(king_move));
+        assert!(restricted.best_score < unrestricted.best_score);
+
+        // An illegal or empty root move list is an error rather than a stalemate
+        let illegal = Move::new(TileIndex::new(4), TileIndex::new(63), None, None);
+        assert_eq!(
+            searcher.get_best_move_restricted(&mut position, &[capture, illegal.clone()], 3).unwrap_err(),
+            SearchError::IllegalRootMove(illegal)
+        );
+        assert_eq!(
+            searcher.get_best_move_restricted(&mut position, &[], 3).unwrap_err(),
+            SearchError::NoRootMoves
+        )
+    }
+
+    #[test]
+    fn test_search_stats() {
+        let mut searcher = test_searcher();
+        let mut position = Position::new_traditional();
+        let first = searcher.get_best_move(&mut position, 3).stats;
+        assert!(first.nodes > searcher.movegen.get_legal_moves(&mut position).len());
+        assert_eq!(first.depth_reached, 3);
+        assert_eq!(first.nps, Searcher::nodes_per_second(first.nodes, first.time_elapsed));
+
+        // A second search starts counting from zero, and the warm table only makes it smaller
+        let second = searcher.get_best_move(&mut position, 3).stats;
+        assert!(second.nodes > 0 && second.nodes <= first.nodes);
+
+        let timed = searcher.search_with_info(&mut position, 2, None, &mut |_info| {}).stats;
+        assert_eq!(timed.depth_reached, 2);
+        assert!(timed.nodes < first.nodes)
+    }
+
+    #[test]
+    fn test_forced_reply_extends_the_line() {
+        // After Ra8+ the only reply is Kh7, so a depth 2 search still sees White's next move
+        let mut searcher = test_searcher();
+        let mut position = Position::from_string("R5K2Q44p8k w -".to_string());
+        let mut infos = vec![];
+        searcher.search_with_info(&mut position, 2, None, &mut |info| infos.push(info.clone()));
+        let last_info = infos.last().unwrap();
+        assert_eq!(last_info.depth, 2);
+        assert_eq!(last_info.seldepth, 3);
+        assert_eq!(last_info.pv.len(), 3);
+        assert_eq!(
+            last_info.pv[..2],
+            [
+                Move::new(TileIndex::new(0), TileIndex::new(56), None, None),
+                Move::new(TileIndex::new(63), TileIndex::new(55), None, None)
+            ]
+        )
     }
 }
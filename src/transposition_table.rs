@@ -1,5 +1,5 @@
 
-use crate::chess_move::Move;
+use crate::chess_move::{Move, PackedMove};
 
 
 const TABLE_SIZE: usize = 1_000_000;
@@ -17,7 +17,7 @@ pub struct Entry {
     pub score: i32,
     pub depth: u8,
     pub flag: Flag,
-    pub best_move: Option<Move>
+    pub best_move: Option<PackedMove> // Packed to keep the table's footprint small
 }
 
 pub struct TranspositionTable {
@@ -53,8 +53,22 @@ impl TranspositionTable {
         None
     }
 
+    pub fn best_move(&self, zobrist_key: u64) -> Option<PackedMove> {
+        let index = self.get_index(zobrist_key);
+        match &self.entries[index] {
+            Some(entry) if entry.zobrist_key == zobrist_key => entry.best_move,
+            _ => None
+        }
+    }
+
+    pub fn hashfull(&self) -> u16 {
+        // Permille of the table in use, estimated from the first thousand entries like UCI engines do
+        self.entries.iter().take(1000).filter(|entry| entry.is_some()).count() as u16
+    }
+
     pub fn store(&mut self, zobrist_key: u64, score: i32, depth: u8, flag: Flag, best_move: Option<Move>) {
         let index = self.get_index(zobrist_key);
+        let best_move = best_move.as_ref().map(PackedMove::pack);
         let new_entry = Entry { zobrist_key, score, depth, flag, best_move };
         if let Some(existing) = &self.entries[index] {
             if existing.zobrist_key == zobrist_key || depth >= existing.depth {
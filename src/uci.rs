@@ -0,0 +1,196 @@
+use std::io::Write;
+use std::sync::{Arc, Mutex};
+
+use crate::graph_boards::board_kind::BoardKind;
+use crate::move_generator::MoveTables;
+use crate::notation::{coordinate_to_move, move_to_coordinate, position_from_fen};
+use crate::piece_set::Color;
+use crate::position::Position;
+use crate::search_handle::SearchHandle;
+use crate::searcher::{Score, SearchInfo, SearchResult, Searcher, MAX_TIMED_DEPTH};
+
+
+// Used when "go" gives no depth or time limit at all
+const DEFAULT_GO_DEPTH: u8 = 4;
+
+// Universal Chess Interface for the traditional board, the only board standard GUIs understand
+pub struct UciEngine<W: Write + Send + 'static> {
+    out: Arc<Mutex<W>>, // Shared with the search thread, which streams info lines and the best move
+    move_tables: Arc<MoveTables>,
+    searcher: Option<Searcher>, // None while a search has it
+    search: Option<SearchHandle>,
+    infinite: bool, // The running search came from "go infinite", so its best move waits for stop
+    position: Position
+}
+
+impl<W: Write + Send + 'static> UciEngine<W> {
+    pub fn new(out: W) -> Self {
+        Self {
+            out: Arc::new(Mutex::new(out)),
+            move_tables: BoardKind::Traditional.shared_move_tables(),
+            searcher: Some(Searcher::new(BoardKind::Traditional.shared_move_tables())),
+            search: None,
+            infinite: false,
+            position: Position::new_traditional()
+        }
+    }
+
+    pub fn handle_command(&mut self, line: &str) -> std::io::Result<bool> {
+        // Returns false once the GUI asks to quit
+        let tokens: Vec<&str> = line.split_whitespace().collect();
+        match tokens.first() {
+            Some(&"uci") => {
+                write_line(&self.out, "id name aperiodic-chess")?;
+                write_line(&self.out, "id author Andy Williams")?;
+                write_line(&self.out, "uciok")?;
+            },
+            Some(&"isready") => write_line(&self.out, "readyok")?, // Answered straight away, even mid search
+            Some(&"ucinewgame") => {
+                self.finish_search();
+                self.searcher = Some(Searcher::new(BoardKind::Traditional.shared_move_tables()));
+                self.position = Position::new_traditional();
+            },
+            Some(&"position") => {
+                self.finish_search();
+                self.set_position(&tokens[1..]);
+            },
+            Some(&"go") => {
+                self.finish_search();
+                self.go(&tokens[1..]);
+            },
+            Some(&"stop") => self.stop_search(),
+            Some(&"quit") => {
+                self.stop_search();
+                return Ok(false)
+            },
+            _ => {} // Unknown commands are ignored, as the protocol asks
+        }
+        Ok(true)
+    }
+
+    fn stop_search(&mut self) {
+        if let Some(search) = &self.search {
+            search.stop();
+        }
+        self.finish_search();
+    }
+
+    fn finish_search(&mut self) {
+        // Waits for a search the GUI didn't stop, since the searcher is needed again
+        // Its best move is out by the time this returns, an infinite search is stopped and answered here
+        if let Some(mut search) = self.search.take() {
+            if self.infinite {
+                search.stop();
+            }
+            let result = loop {
+                match search.poll() {
+                    Some(result) => break result,
+                    None => std::thread::sleep(std::time::Duration::from_millis(1))
+                }
+            };
+            if self.infinite {
+                let _ = write_line(&self.out, &bestmove_line(&result));
+            }
+            self.searcher = Some(search.into_searcher());
+        }
+    }
+
+    fn set_position(&mut self, tokens: &[&str]) {
+        let moves_idx = tokens.iter().position(|token| *token == "moves").unwrap_or(tokens.len());
+        let position = match tokens.first() {
+            Some(&"startpos") => Some(Position::new_traditional()),
+            Some(&"fen") => position_from_fen(&tokens[1..moves_idx].join(" ")),
+            _ => None
+        };
+        // A position that can't arise in play would break the search, so the old one is kept instead
+        let mut position = match position {
+            Some(position) if position.validate(&self.move_tables).is_ok() => position,
+            _ => return
+        };
+        for coordinate in tokens.iter().skip(moves_idx + 1) {
+            match coordinate_to_move(&mut position, coordinate, &self.move_tables) {
+                Some(chess_move) => position.make_legal_move(&chess_move),
+                None => break // Anything after an illegal move can't be applied
+            }
+        }
+        self.position = position;
+    }
+
+    fn go(&mut self, tokens: &[&str]) {
+        let value_of = |name: &str| -> Option<u64> {
+            let idx = tokens.iter().position(|token| *token == name)?;
+            tokens.get(idx + 1)?.parse().ok()
+        };
+        let (clock, increment) = match self.position.active_player {
+            Color::White => ("wtime", "winc"),
+            Color::Black => ("btime", "binc")
+        };
+        let time_ms = match (value_of("movetime"), value_of(clock)) {
+            (Some(movetime), _) => Some(movetime),
+            (None, Some(remaining)) => Some(Searcher::allocate_time(
+                remaining,
+                value_of(increment).unwrap_or(0),
+                value_of("movestogo").unwrap_or(0) as u32
+            )),
+            (None, None) => None
+        };
+        // Infinite analysis ignores any other limit and runs until stop
+        self.infinite = tokens.contains(&"infinite");
+        let time_ms = match self.infinite {
+            true => None,
+            false => time_ms
+        };
+        let max_depth = match (value_of("depth"), time_ms) {
+            _ if self.infinite => MAX_TIMED_DEPTH,
+            (Some(depth), _) => depth.clamp(1, MAX_TIMED_DEPTH as u64) as u8,
+            (None, Some(_)) => MAX_TIMED_DEPTH,
+            (None, None) => DEFAULT_GO_DEPTH
+        };
+
+        // Runs in the background so stop can be read while it searches
+        let searcher = self.searcher.take().expect("finish_search hands the searcher back");
+        let info_out = self.out.clone();
+        let done_out = self.out.clone();
+        let infinite = self.infinite;
+        self.search = Some(SearchHandle::spawn_reporting(
+            searcher,
+            self.position.clone(),
+            max_depth,
+            time_ms,
+            move |info| {
+                let _ = write_line(&info_out, &info_line(info));
+            },
+            move |result| {
+                if !infinite {
+                    let _ = write_line(&done_out, &bestmove_line(result));
+                }
+            }
+        ));
+    }
+}
+
+fn write_line<W: Write>(out: &Mutex<W>, line: &str) -> std::io::Result<()> {
+    let mut out = out.lock().unwrap();
+    writeln!(out, "{}", line)?;
+    out.flush()
+}
+
+fn bestmove_line(result: &SearchResult) -> String {
+    let bestmove = match &result.best_move {
+        Some(best_move) => move_to_coordinate(best_move),
+        None => "0000".to_string() // No legal moves, the null move is the protocol's answer
+    };
+    format!("bestmove {}", bestmove)
+}
+
+fn info_line(info: &SearchInfo) -> String {
+    let score = match info.score {
+        Score::Cp(centipawns) => format!("cp {}", centipawns),
+        Score::Mate(moves) => format!("mate {}", moves)
+    };
+    let pv: Vec<String> = info.pv.iter().map(move_to_coordinate).collect();
+    format!(
+        "info depth {} seldepth {} score {} nodes {} nps {} time {} hashfull {} pv {}",
+        info.depth, info.seldepth, score, info.nodes, info.nps, info.time_ms, info.hashfull, pv.join(" ")
+    )
+}
@@ -0,0 +1,18 @@
+// Only uses the library, so `cargo test --no-default-features` checks the core still builds without Bevy
+use aperiodic_chess::graph_boards::board_kind::BoardKind;
+use aperiodic_chess::position::Position;
+use aperiodic_chess::searcher::Searcher;
+
+
+#[test]
+fn test_core_perft_and_search() {
+    let move_tables = BoardKind::Traditional.move_tables();
+    let mut position = Position::new_traditional();
+    assert_eq!(
+        move_tables.perft(&mut position, 3),
+        8902
+    );
+
+    let mut searcher = Searcher::new(BoardKind::Traditional.shared_move_tables());
+    assert!(searcher.get_best_move(&mut position, 2).best_move.is_some())
+}
@@ -0,0 +1,122 @@
+// Drives the uci binary the way a chess GUI would, over stdin and stdout
+use std::io::{BufRead, BufReader, Write};
+use std::process::{Child, Command, Stdio};
+use std::sync::mpsc::{self, Receiver};
+use std::thread;
+use std::time::Duration;
+
+use aperiodic_chess::graph_boards::board_kind::BoardKind;
+use aperiodic_chess::notation::{coordinate_to_move, position_from_fen};
+
+
+fn spawn_engine() -> (Child, Receiver<String>) {
+    // Lines are read on their own thread, so a test can wait on them with a timeout
+    let mut engine = Command::new(env!("CARGO_BIN_EXE_uci"))
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+    let stdout = engine.stdout.take().unwrap();
+    let (sender, receiver) = mpsc::channel();
+    thread::spawn(move || {
+        for line in BufReader::new(stdout).lines() {
+            if sender.send(line.unwrap()).is_err() {
+                break
+            }
+        }
+    });
+    return (engine, receiver)
+}
+
+fn send(engine: &mut Child, commands: &str) {
+    let stdin = engine.stdin.as_mut().unwrap();
+    stdin.write_all(commands.as_bytes()).unwrap();
+    stdin.flush().unwrap();
+}
+
+fn lines_until_bestmove(receiver: &Receiver<String>, timeout: Duration) -> Vec<String> {
+    let mut lines = vec![];
+    loop {
+        let line = receiver.recv_timeout(timeout).expect("no bestmove in time");
+        let done = line.starts_with("bestmove ");
+        lines.push(line);
+        if done {
+            return lines
+        }
+    }
+}
+
+#[test]
+fn test_uci_session_emits_legal_bestmove() {
+    let (mut engine, receiver) = spawn_engine();
+    send(&mut engine, "uci\nisready\nucinewgame\nposition startpos moves e2e4 e7e5 g1f3\ngo depth 3\n");
+    let lines = lines_until_bestmove(&receiver, Duration::from_secs(30));
+    send(&mut engine, "quit\n");
+    engine.wait().unwrap();
+
+    assert!(lines.contains(&"uciok".to_string()));
+    assert!(lines.contains(&"readyok".to_string()));
+    assert_eq!(
+        lines.iter().filter(|line| line.starts_with("info depth")).count(),
+        3
+    );
+    let bestmove = lines.last().unwrap().strip_prefix("bestmove ").unwrap();
+    let mut position = position_from_fen("rnbqkbnr/pppp1ppp/8/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R b KQkq - 1 2").unwrap();
+    assert!(coordinate_to_move(&mut position, bestmove, &BoardKind::Traditional.move_tables()).is_some())
+}
+
+#[test]
+fn test_uci_streams_info_and_obeys_stop() {
+    // A movetime far longer than the test, so only stop can end the search
+    let (mut engine, receiver) = spawn_engine();
+    send(&mut engine, "position startpos\ngo movetime 600000\n");
+    let first_info = receiver.recv_timeout(Duration::from_secs(30)).expect("info wasn't streamed during the search");
+    assert!(first_info.starts_with("info depth 1 "));
+
+    send(&mut engine, "stop\n");
+    let lines = lines_until_bestmove(&receiver, Duration::from_secs(5));
+    send(&mut engine, "quit\n");
+    engine.wait().unwrap();
+
+    let bestmove = lines.last().unwrap().strip_prefix("bestmove ").unwrap();
+    let mut position = position_from_fen("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1").unwrap();
+    assert!(coordinate_to_move(&mut position, bestmove, &BoardKind::Traditional.move_tables()).is_some())
+}
+
+#[test]
+fn test_uci_keeps_the_old_position_after_a_bad_fen() {
+    // No kings at all, so the search would have nothing to work with
+    let (mut engine, receiver) = spawn_engine();
+    send(&mut engine, "position startpos moves e2e4\nposition fen 8/8/8/8/8/8/8/8 w - - 0 1\nisready\n");
+    assert_eq!(
+        receiver.recv_timeout(Duration::from_secs(5)).expect("the bad fen stopped the engine"),
+        "readyok"
+    );
+    send(&mut engine, "go depth 2\n");
+    let lines = lines_until_bestmove(&receiver, Duration::from_secs(30));
+    send(&mut engine, "quit\n");
+    engine.wait().unwrap();
+
+    let bestmove = lines.last().unwrap().strip_prefix("bestmove ").unwrap();
+    let mut position = position_from_fen("rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1").unwrap();
+    assert!(coordinate_to_move(&mut position, bestmove, &BoardKind::Traditional.move_tables()).is_some())
+}
+
+#[test]
+fn test_uci_infinite_waits_for_stop() {
+    // Already checkmated, so the search ends at once but the best move still has to wait for stop
+    let (mut engine, receiver) = spawn_engine();
+    send(&mut engine, "position fen rnb1kbnr/pppp1ppp/8/4p3/6Pq/5P2/PPPPP2P/RNBQKBNR w KQkq - 1 3\ngo infinite\n");
+    thread::sleep(Duration::from_millis(200));
+    send(&mut engine, "isready\n");
+    assert_eq!(
+        receiver.recv_timeout(Duration::from_secs(5)).unwrap(),
+        "readyok"
+    );
+
+    send(&mut engine, "stop\n");
+    let lines = lines_until_bestmove(&receiver, Duration::from_secs(5));
+    send(&mut engine, "quit\n");
+    engine.wait().unwrap();
+    assert_eq!(lines, vec!["bestmove 0000".to_string()])
+}